@@ -0,0 +1,193 @@
+app-title = AI Overlay
+
+## Top bar
+health-checking = Checking ...
+health-checking-details = Checking the Ollama server ...
+health-online = Online
+health-offline = Offline
+health-unreachable = Ollama is unreachable: { $error }
+health-no-model-loaded = No model loaded
+health-loaded-model = { $model } ({ $megabytes } MB in VRAM)
+health-version = Ollama { $version } - { $latency } ms
+nav-chats = Chats
+nav-search = Search
+nav-compare = Compare
+nav-settings = Settings
+nav-back = Back
+
+## Home
+prompt-placeholder = AI Message
+context-summarizing = Summarizing older messages ...
+context-usage = Context: ~{ $used } / { $total } tokens
+reply-sources = Sources: { $sources }
+reply-tools = Tools: { $tools }
+reply-title = AI's response :
+branch-prompt = Prompt
+branch-previous = Previous alternative
+branch-next = Next alternative
+branch-reply = Reply
+edit-prompt = Edit prompt
+editing-prompt = Editing previous prompt
+cancel = Cancel
+expand = Expand
+collapse = Collapse
+regenerate = Regenerate
+error-title = There was an error :(
+error-server-down = Ollama is not running, start it with `ollama serve` ({ $error })
+error-timeout = Ollama took too long to answer, raise the request timeout in the settings or pick a smaller model
+error-model-not-found = Model { $model } is not installed, pull it with `ollama pull { $model }` or pick another one
+error-out-of-memory = Not enough memory to run the model, pick a smaller one or lower the context window ({ $error })
+error-server = Ollama failed with status { $status }: { $error }
+error-invalid-json-reply = The reply is not the expected JSON after { $attempts } attempts:
+    { $errors }
+error-cannot-read = Cannot read { $path }: { $error }
+in-progress = In progress ...
+retry-status = Retry { $attempt } of { $max } in { $seconds } s: { $error }
+model-missing = Model { $model } is not installed.
+model-pulling = Pulling { $model }: { $status }
+model-pull-starting = Starting download
+model-pull = Pull { $model }
+model-pull-failed = Pulling { $model } failed: { $error }
+model-use-other = Or use:
+tools-request = The AI wants to use tools:
+tools-allow = Allow
+tools-deny = Deny
+
+## Conversations
+conversation-new = + New conversation
+conversation-title = Conversation title
+conversation-untitled = New conversation
+conversation-rename = Rename
+conversation-pin = Pin
+conversation-unpin = Unpin
+conversation-delete = Delete
+conversation-export = Export:
+conversation-exported = Exported to { $path }
+conversation-export-failed = Export failed: { $error }
+conversations-save-failed = The conversations could not be saved: { $error }
+import-placeholder = Transcript to import
+import = Import
+import-failed = Import failed: { $error }
+import-done = Imported { $messages } messages into "{ $title }"
+import-skipped = Skipped { $count } entries:
+import-entry-skipped = Entry { $entry }: { $error }
+import-default-title = Imported conversation
+import-invalid-json = Invalid JSON: { $error }
+import-unrecognized = Expected an exported transcript or a `messages` array
+import-invalid-transcript = Invalid transcript: { $error }
+import-newer-version = Transcript version { $version } is newer than supported version { $supported }
+import-no-valid-message = No valid message to import
+import-not-an-object = not an object
+import-unsupported-role = unsupported role `{ $role }`
+import-missing-role = missing role
+import-content-not-text = content is not text
+import-missing-content = missing content
+import-empty-content = empty content
+conversation-no-root = The conversation has no root message
+conversation-root-has-parent = The root message has a parent
+conversation-orphan-message = Message { $id } is not a child of its parent
+conversation-invalid-child = Message { $id } has an invalid child
+conversation-missing-branch = Message { $id } selects a missing branch
+conversation-cycle = Message { $id } is reached twice from the root
+conversation-unreachable-message = Message { $id } cannot be reached from the root
+
+## Search
+search-placeholder = Search conversations, model:name
+search-no-match = No match
+
+## Compare
+compare-placeholder = Prompt to compare
+compare-waiting = Waiting ...
+compare-failed = Failed
+compare-use-answer = Use this answer
+
+## Usage
+usage-model = Model
+usage-replies = Replies
+usage-prompt-tokens = Prompt tok
+usage-reply-tokens = Reply tok
+usage-tokens-per-second = Avg tok/s
+usage-average-time = Avg time
+usage-last-used = Last used
+usage-empty = No reply recorded yet
+stats-tokens = Prompt { $prompt } tok - Reply { $reply } tok
+stats-rate = { $rate } tok/s
+stats-durations = Load { $load } s - Total { $total } s
+
+## Settings
+settings-model = AI model
+settings-keep-alive = Keep the model loaded for
+settings-keep-alive-placeholder = Server default
+settings-unload = Unload now
+model-loading = Loading the model ...
+model-loaded = { $model } is loaded
+model-loading-failed = Loading the model failed: { $error }
+model-unloaded = { $model } is unloaded
+model-unloading-failed = Unloading the model failed: { $error }
+settings-language = Language
+settings-language-system = System language
+settings-reply-language = Replies
+settings-reply-in-language = Answer in the interface language
+settings-theme = Theme
+settings-theme-follow-system = Follow system
+settings-theme-reload = Reload
+theme-dark = Dark
+theme-light = Light
+theme-high-contrast = High contrast
+settings-themes-folder = Themes are read from the { $folder } folder, their font size applies on the next start
+settings-opacity = Background opacity ({ $percent } %)
+settings-corner-radius = Corner radius ({ $pixels } px)
+settings-border-color = Border color
+settings-border-color-placeholder = Theme default, e.g. #3c3c3c
+settings-border-color-invalid = Invalid color, write it as #rrggbb
+settings-reset-appearance = Use the theme appearance
+settings-accessibility = Accessibility
+settings-high-contrast = High contrast
+settings-ui-font = Interface font
+settings-ui-font-hint = The interface font is applied on the next start
+settings-reply-font = Reply font
+settings-code-font = Code font
+settings-font-default = Default
+settings-zoom = Zoom ({ $percent } %)
+settings-zoom-hint = Ctrl+Plus and Ctrl+Minus zoom in and out, Ctrl+0 resets the zoom
+settings-context-window = Context window (tokens)
+settings-context-window-placeholder = Model default ({ $tokens })
+settings-context-window-invalid = Enter a number of at least { $tokens } tokens
+settings-timeout = Request timeout (seconds)
+settings-timeout-placeholder = Seconds
+settings-retries = Retries when Ollama fails
+settings-retries-placeholder = Retries
+settings-documents = Documents folder
+settings-documents-placeholder = Folder of .md and .txt files
+settings-embedding-model = Embedding model
+settings-index-documents = Index documents
+documents-indexing = Indexing documents ...
+documents-indexed = Indexed { $chunks } chunks from { $files } files ({ $new } new)
+documents-indexing-failed = Indexing failed: { $error }
+documents-search-failed = Answered without the documents, searching them failed: { $error }
+documents-no-directory = No documents directory configured
+settings-tools = Tools
+settings-tools-enabled = Let the AI call tools
+settings-workspace = Workspace folder
+settings-workspace-placeholder = Folder the tools can read
+settings-allowed-commands = Allowed commands
+settings-json-output = JSON output
+settings-json-only = Answer with JSON only
+settings-json-schema = JSON schema
+settings-json-schema-placeholder = Inline schema or path to a .json file
+json-schema-cannot-read = Cannot read schema { $path }: { $error }
+json-schema-invalid = Invalid JSON schema: { $error }
+settings-when-closed = When closed
+settings-hide-on-close = Hide instead of quitting
+settings-window = Window
+settings-always-on-top = Keep above other windows
+settings-placement = Open at
+settings-when-unfocused = When another window is focused
+settings-hide = Hide
+settings-usage = Usage statistics
+quit = Quit
+
+placement-center = Center
+placement-top-center = Top center
+placement-cursor-monitor = Monitor of the cursor
+placement-last-position = Last position
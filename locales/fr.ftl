@@ -0,0 +1,193 @@
+app-title = AI Overlay
+
+## Top bar
+health-checking = Vérification ...
+health-checking-details = Vérification du serveur Ollama ...
+health-online = En ligne
+health-offline = Hors ligne
+health-unreachable = Ollama est injoignable : { $error }
+health-no-model-loaded = Aucun modèle chargé
+health-loaded-model = { $model } ({ $megabytes } Mo en VRAM)
+health-version = Ollama { $version } - { $latency } ms
+nav-chats = Discussions
+nav-search = Recherche
+nav-compare = Comparer
+nav-settings = Paramètres
+nav-back = Retour
+
+## Home
+prompt-placeholder = Message à l'IA
+context-summarizing = Résumé des anciens messages ...
+context-usage = Contexte : ~{ $used } / { $total } jetons
+reply-sources = Sources : { $sources }
+reply-tools = Outils : { $tools }
+reply-title = Réponse de l'IA :
+branch-prompt = Question
+branch-previous = Version précédente
+branch-next = Version suivante
+branch-reply = Réponse
+edit-prompt = Modifier la question
+editing-prompt = Modification de la question précédente
+cancel = Annuler
+expand = Agrandir
+collapse = Réduire
+regenerate = Régénérer
+error-title = Une erreur est survenue :(
+error-server-down = Ollama ne tourne pas, lancez-le avec `ollama serve` ({ $error })
+error-timeout = Ollama a mis trop de temps à répondre, augmentez le délai des requêtes dans les paramètres ou choisissez un modèle plus petit
+error-model-not-found = Le modèle { $model } n'est pas installé, téléchargez-le avec `ollama pull { $model }` ou choisissez-en un autre
+error-out-of-memory = Pas assez de mémoire pour le modèle, choisissez-en un plus petit ou réduisez la fenêtre de contexte ({ $error })
+error-server = Ollama a échoué avec le statut { $status } : { $error }
+error-invalid-json-reply = La réponse n'est pas le JSON attendu après { $attempts } tentatives :
+    { $errors }
+error-cannot-read = Impossible de lire { $path } : { $error }
+in-progress = En cours ...
+retry-status = Nouvel essai { $attempt } sur { $max } dans { $seconds } s : { $error }
+model-missing = Le modèle { $model } n'est pas installé.
+model-pulling = Téléchargement de { $model } : { $status }
+model-pull-starting = Début du téléchargement
+model-pull = Télécharger { $model }
+model-pull-failed = Le téléchargement de { $model } a échoué : { $error }
+model-use-other = Ou utiliser :
+tools-request = L'IA veut utiliser des outils :
+tools-allow = Autoriser
+tools-deny = Refuser
+
+## Conversations
+conversation-new = + Nouvelle discussion
+conversation-title = Titre de la discussion
+conversation-untitled = Nouvelle conversation
+conversation-rename = Renommer
+conversation-pin = Épingler
+conversation-unpin = Désépingler
+conversation-delete = Supprimer
+conversation-export = Exporter :
+conversation-exported = Exportée dans { $path }
+conversation-export-failed = L'export a échoué : { $error }
+conversations-save-failed = Les conversations n'ont pas pu être enregistrées : { $error }
+import-placeholder = Transcription à importer
+import = Importer
+import-failed = L'import a échoué : { $error }
+import-done = { $messages } messages importés dans « { $title } »
+import-skipped = { $count } entrées ignorées :
+import-entry-skipped = Entrée { $entry } : { $error }
+import-default-title = Conversation importée
+import-invalid-json = JSON invalide : { $error }
+import-unrecognized = Une transcription exportée ou un tableau `messages` est attendu
+import-invalid-transcript = Transcription invalide : { $error }
+import-newer-version = La version { $version } de la transcription est plus récente que la version { $supported } prise en charge
+import-no-valid-message = Aucun message valide à importer
+import-not-an-object = pas un objet
+import-unsupported-role = rôle `{ $role }` non pris en charge
+import-missing-role = rôle manquant
+import-content-not-text = le contenu n'est pas du texte
+import-missing-content = contenu manquant
+import-empty-content = contenu vide
+conversation-no-root = La conversation n'a pas de message racine
+conversation-root-has-parent = Le message racine a un parent
+conversation-orphan-message = Le message { $id } n'est pas un enfant de son parent
+conversation-invalid-child = Le message { $id } a un enfant invalide
+conversation-missing-branch = Le message { $id } sélectionne une branche manquante
+conversation-cycle = Le message { $id } est atteint deux fois depuis la racine
+conversation-unreachable-message = Le message { $id } n'est pas atteignable depuis la racine
+
+## Search
+search-placeholder = Rechercher dans les discussions, model:nom
+search-no-match = Aucun résultat
+
+## Compare
+compare-placeholder = Question à comparer
+compare-waiting = En attente ...
+compare-failed = Échec
+compare-use-answer = Garder cette réponse
+
+## Usage
+usage-model = Modèle
+usage-replies = Réponses
+usage-prompt-tokens = Jetons question
+usage-reply-tokens = Jetons réponse
+usage-tokens-per-second = Jetons/s moy.
+usage-average-time = Durée moy.
+usage-last-used = Dernière utilisation
+usage-empty = Aucune réponse enregistrée
+stats-tokens = Question { $prompt } jetons - Réponse { $reply } jetons
+stats-rate = { $rate } jetons/s
+stats-durations = Chargement { $load } s - Total { $total } s
+
+## Settings
+settings-model = Modèle d'IA
+settings-keep-alive = Garder le modèle chargé pendant
+settings-keep-alive-placeholder = Valeur du serveur
+settings-unload = Décharger
+model-loading = Chargement du modèle ...
+model-loaded = { $model } est chargé
+model-loading-failed = Le chargement du modèle a échoué : { $error }
+model-unloaded = { $model } est déchargé
+model-unloading-failed = Le déchargement du modèle a échoué : { $error }
+settings-language = Langue
+settings-language-system = Langue du système
+settings-reply-language = Réponses
+settings-reply-in-language = Répondre dans la langue de l'interface
+settings-theme = Thème
+settings-theme-follow-system = Suivre le système
+settings-theme-reload = Recharger
+theme-dark = Sombre
+theme-light = Clair
+theme-high-contrast = Contraste élevé
+settings-themes-folder = Les thèmes sont lus dans le dossier { $folder }, leur taille de police s'applique au prochain démarrage
+settings-opacity = Opacité du fond ({ $percent } %)
+settings-corner-radius = Arrondi des coins ({ $pixels } px)
+settings-border-color = Couleur de la bordure
+settings-border-color-placeholder = Celle du thème, ex. #3c3c3c
+settings-border-color-invalid = Couleur invalide, écrivez-la #rrvvbb
+settings-reset-appearance = Reprendre l'apparence du thème
+settings-accessibility = Accessibilité
+settings-high-contrast = Contraste élevé
+settings-ui-font = Police de l'interface
+settings-ui-font-hint = La police de l'interface s'applique au prochain démarrage
+settings-reply-font = Police des réponses
+settings-code-font = Police du code
+settings-font-default = Par défaut
+settings-zoom = Zoom ({ $percent } %)
+settings-zoom-hint = Ctrl+Plus et Ctrl+Moins zooment et dézooment, Ctrl+0 rétablit le zoom
+settings-context-window = Fenêtre de contexte (jetons)
+settings-context-window-placeholder = Celle du modèle ({ $tokens })
+settings-context-window-invalid = Saisissez un nombre d'au moins { $tokens } jetons
+settings-timeout = Délai des requêtes (secondes)
+settings-timeout-placeholder = Secondes
+settings-retries = Nouveaux essais si Ollama échoue
+settings-retries-placeholder = Essais
+settings-documents = Dossier de documents
+settings-documents-placeholder = Dossier de fichiers .md et .txt
+settings-embedding-model = Modèle d'embeddings
+settings-index-documents = Indexer les documents
+documents-indexing = Indexation des documents ...
+documents-indexed = { $chunks } extraits indexés depuis { $files } fichiers ({ $new } nouveaux)
+documents-indexing-failed = L'indexation a échoué : { $error }
+documents-search-failed = Réponse donnée sans les documents, leur recherche a échoué : { $error }
+documents-no-directory = Aucun dossier de documents configuré
+settings-tools = Outils
+settings-tools-enabled = Laisser l'IA utiliser des outils
+settings-workspace = Dossier de travail
+settings-workspace-placeholder = Dossier lisible par les outils
+settings-allowed-commands = Commandes autorisées
+settings-json-output = Sortie JSON
+settings-json-only = Répondre uniquement en JSON
+settings-json-schema = Schéma JSON
+settings-json-schema-placeholder = Schéma en ligne ou chemin d'un fichier .json
+json-schema-cannot-read = Impossible de lire le schéma { $path } : { $error }
+json-schema-invalid = Schéma JSON invalide : { $error }
+settings-when-closed = À la fermeture
+settings-hide-on-close = Masquer au lieu de quitter
+settings-window = Fenêtre
+settings-always-on-top = Garder au-dessus des autres fenêtres
+settings-placement = Ouvrir
+settings-when-unfocused = Quand une autre fenêtre est active
+settings-hide = Masquer
+settings-usage = Statistiques d'utilisation
+quit = Quitter
+
+placement-center = Au centre
+placement-top-center = En haut au centre
+placement-cursor-monitor = Sur l'écran du curseur
+placement-last-position = À la dernière position
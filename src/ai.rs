@@ -1,42 +1,506 @@
+use std::time::{Duration, Instant};
+
 use iced::futures::lock::Mutex;
 use lazy_static::lazy_static;
-use ollama_rs::generation::chat::request::ChatMessageRequest;
-use ollama_rs::generation::chat::ChatMessage;
+use ollama_rs::generation::chat::{
+    ChatMessage, MessageRole,
+};
 use ollama_rs::Ollama;
+use serde_json::{json, Value};
+
+use crate::{
+    api::{
+        self, AiError, ApiMessage, ChatRequest, ToolCall,
+    },
+    config::ApplicationSettings,
+    i18n,
+    rag::{retrieve, Citation},
+    structured, t, tools,
+    usage::GenerationStats,
+};
 
-use crate::debug;
+/// Context window used by Ollama when neither the model nor
+/// the settings define one
+pub const DEFAULT_NUM_CTX: u32 = 2048;
+/// Smallest context window the settings accept, below which
+/// every reply would need a summary
+pub const MIN_NUM_CTX: u32 = 512;
+/// Rounds of tool calls after which the model has to answer
+const MAX_TOOL_ROUNDS: usize = 5;
+/// Times a reply not matching the JSON schema is asked again
+const MAX_JSON_RETRIES: usize = 2;
+/// Pause before the first resend of a failed request, doubled
+/// for each following one
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 lazy_static! {
     static ref OLLAMA: Mutex<Ollama> =
-        Mutex::new(Ollama::new_default_with_history(30));
+        Mutex::new(Ollama::default());
 }
 
-pub async fn ask_ai(
-    message: String,
-    settings: crate::config::ApplicationSettings,
-) -> Result<String, String> {
-    let mut ollama = OLLAMA.lock().await;
-    debug!(&message);
-    debug!(ollama
-        .get_messages_history(settings.ai_model.clone()));
+/// Answer of the AI to a prompt of the conversation
+#[derive(Debug, Clone)]
+pub struct AiReply {
+    pub content: String,
+    pub model: String,
+    /// Documents passages the answer was given
+    pub citations: Vec<Citation>,
+    /// Tool calls the answer was given the results of
+    pub tools_used: Vec<String>,
+    /// Answer given in JSON mode, validated
+    pub structured: bool,
+    /// Counters of every request the answer took
+    pub stats: GenerationStats,
+    /// Why the documents could not be searched, the answer
+    /// being given without them
+    pub documents_error: Option<String>,
+}
 
-    ollama
-        .send_chat_messages_with_history(
-            ChatMessageRequest::new(
-                settings.ai_model.clone(),
-                vec![ChatMessage::user(message)],
+/// Answer of a model being compared, with how fast it came
+#[derive(Debug, Clone)]
+pub struct ModelAnswer {
+    pub content: String,
+    pub latency: Duration,
+    pub stats: GenerationStats,
+}
+
+/// What the AI came back with: an answer, or tools it wants
+/// to call first
+#[derive(Debug, Clone)]
+pub enum AiOutcome {
+    Reply(AiReply),
+    ToolRequest(ToolRequest),
+}
+
+/// Tool calls waiting for the user's approval, along with the
+/// exchange to resume once they ran
+#[derive(Debug, Clone)]
+pub struct ToolRequest {
+    pub calls: Vec<ToolCall>,
+    messages: Vec<ApiMessage>,
+    citations: Vec<Citation>,
+    tools_used: Vec<String>,
+    round: usize,
+    /// Schema the final answer has to match in JSON mode
+    schema: Option<Value>,
+    stats: GenerationStats,
+    documents_error: Option<String>,
+}
+
+/// Request to the AI, kept so it can be sent again when it
+/// fails
+#[derive(Debug, Clone)]
+pub enum AiRequest {
+    /// Answer the last prompt of these messages
+    Ask(Vec<ChatMessage>),
+    /// Carry on once tool calls ran
+    Continue(ToolRequest),
+}
+
+/// Resend of a failed request, pending while it waits
+#[derive(Debug, Clone)]
+pub struct RetryState {
+    /// 1-based number of the resend
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay: Duration,
+    pub error: String,
+}
+
+impl RetryState {
+    pub fn new(
+        attempt: u32,
+        max_attempts: u32,
+        error: &AiError,
+    ) -> Self {
+        Self {
+            attempt,
+            max_attempts,
+            delay: api::backoff_delay(
+                attempt,
+                FIRST_RETRY_DELAY,
+                MAX_RETRY_DELAY,
             ),
-            settings.ai_model.clone(),
+            error: error.to_string(),
+        }
+    }
+}
+
+pub async fn send_request(
+    request: AiRequest,
+    settings: ApplicationSettings,
+) -> Result<AiOutcome, AiError> {
+    match request {
+        AiRequest::Ask(messages) => {
+            ask_ai(messages, settings).await
+        }
+        AiRequest::Continue(request) => {
+            converse(request, settings).await
+        }
+    }
+}
+
+/// Answer the last prompt of a conversation, along with the
+/// passages of the user's documents related to it
+async fn ask_ai(
+    mut messages: Vec<ChatMessage>,
+    settings: ApplicationSettings,
+) -> Result<AiOutcome, AiError> {
+    let mut citations = vec![];
+    let mut documents_error = None;
+
+    let prompt = messages.iter().rposition(|message| {
+        message.role == MessageRole::User
+    });
+    if let Some(position) =
+        prompt.filter(|_| settings.documents_dir.is_some())
+    {
+        // Answer without the documents rather than not at
+        // all when they cannot be searched
+        let chunks = retrieve(
+            &messages[position].content,
+            &settings,
         )
         .await
-        .map(|res| res.message.map(|msg| msg.content))
-        .map(|content| content.unwrap_or("".to_string()))
+        .unwrap_or_else(|err| {
+            documents_error = Some(err);
+            vec![]
+        });
+
+        if !chunks.is_empty() {
+            let excerpts = chunks
+                .iter()
+                .enumerate()
+                .map(|(i, chunk)| {
+                    format!(
+                        "[{}] {}\n{}",
+                        i + 1,
+                        chunk.citation,
+                        chunk.text
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n");
+
+            messages.insert(
+                position,
+                ChatMessage::system(format!(
+                    "Excerpts of the user's documents, use them \
+                     when relevant and cite them as [n]:\n\n{excerpts}"
+                )),
+            );
+            citations = chunks
+                .into_iter()
+                .map(|c| c.citation)
+                .collect();
+        }
+    }
+
+    let schema = if settings.json_mode {
+        structured::load_schema(&settings)?
+    } else {
+        None
+    };
+    if settings.json_mode {
+        let instruction = match &schema {
+            Some(schema) => format!(
+                "Answer with a JSON document only, matching \
+                 this JSON schema:\n{schema}"
+            ),
+            None => "Answer with a JSON document only."
+                .to_string(),
+        };
+        messages.insert(
+            prompt.unwrap_or(messages.len()),
+            ChatMessage::system(instruction),
+        );
+    }
+    if let Some(instruction) =
+        language_instruction(&settings)
+    {
+        messages.insert(
+            prompt.unwrap_or(messages.len()),
+            instruction,
+        );
+    }
+
+    let request = ToolRequest {
+        calls: vec![],
+        messages: messages
+            .into_iter()
+            .map(ApiMessage::from)
+            .collect(),
+        citations,
+        tools_used: vec![],
+        round: 0,
+        schema,
+        stats: GenerationStats::default(),
+        documents_error,
+    };
+    converse(request, settings).await
+}
+
+/// Run the tool calls of a request, or tell the model the
+/// user declined them, so it can carry on
+pub async fn run_tools(
+    mut request: ToolRequest,
+    approved: bool,
+    settings: ApplicationSettings,
+) -> ToolRequest {
+    for call in std::mem::take(&mut request.calls) {
+        let output = if approved {
+            request.tools_used.push(tools::describe(&call));
+            tools::run_tool(&call, &settings).await
+        } else {
+            "The user declined to run this tool."
+                .to_string()
+        };
+        request.messages.push(ApiMessage::tool(
+            call.function.name,
+            output,
+        ));
+    }
+
+    request.round += 1;
+    request
+}
+
+async fn converse(
+    mut request: ToolRequest,
+    settings: ApplicationSettings,
+) -> Result<AiOutcome, AiError> {
+    let mut chat_request =
+        chat_request(request.messages.clone(), &settings);
+    if settings.tools_enabled
+        && request.round < MAX_TOOL_ROUNDS
+    {
+        chat_request.tools = tools::definitions();
+    }
+
+    if settings.json_mode {
+        chat_request.format = Some(
+            request.schema.clone().unwrap_or(json!("json")),
+        );
+    }
+
+    let mut retries = 0;
+    loop {
+        let response = api::chat(&chat_request).await?;
+        request.stats.add(&response.stats);
+        let message = response.message;
+
+        if !message.tool_calls.is_empty() {
+            request.calls = message.tool_calls.clone();
+            request.messages.push(message);
+            return Ok(AiOutcome::ToolRequest(request));
+        }
+
+        let errors = if settings.json_mode {
+            structured::validate_reply(
+                &message.content,
+                request.schema.as_ref(),
+            )
+        } else {
+            vec![]
+        };
+
+        if errors.is_empty() {
+            return Ok(AiOutcome::Reply(AiReply {
+                content: message.content,
+                model: settings.ai_model,
+                citations: request.citations,
+                tools_used: request.tools_used,
+                structured: settings.json_mode,
+                stats: request.stats,
+                documents_error: request.documents_error,
+            }));
+        } else if retries == MAX_JSON_RETRIES {
+            return Err(AiError::Other(t!(
+                "error-invalid-json-reply",
+                attempts = retries + 1,
+                errors = errors.join("\n")
+            )));
+        }
+
+        // Ask again, pointing at what was wrong
+        retries += 1;
+        chat_request.messages.push(message);
+        chat_request.messages.push(ApiMessage::from(
+            ChatMessage::user(format!(
+                "Your reply is invalid: {}. Answer again \
+                 with the corrected JSON only.",
+                errors.join("; ")
+            )),
+        ));
+    }
+}
+
+fn chat_request(
+    messages: Vec<ApiMessage>,
+    settings: &ApplicationSettings,
+) -> ChatRequest {
+    let mut request = ChatRequest::new(
+        settings.ai_model.clone(),
+        messages,
+    );
+    if let Some(num_ctx) =
+        settings.num_ctx.filter(|&n| n >= MIN_NUM_CTX)
+    {
+        request.options =
+            Some(json!({ "num_ctx": num_ctx }));
+    }
+    request.timeout =
+        Some(Duration::from_secs(settings.request_timeout));
+    request.keep_alive = keep_alive(&settings.keep_alive);
+    request
+}
+
+/// Keep-alive setting as expected by Ollama, a plain number
+/// being a count of seconds and a negative one meaning
+/// forever
+fn keep_alive(setting: &str) -> Option<Value> {
+    match setting.trim() {
+        "" => None,
+        value => Some(
+            value
+                .parse::<i64>()
+                .map(Value::from)
+                .unwrap_or(json!(value)),
+        ),
+    }
+}
+
+/// Outcome of the last model load or unload, translated
+/// when shown so it follows the interface language
+#[derive(Debug, Clone)]
+pub enum ModelStatus {
+    Loading,
+    Loaded(String),
+    LoadingFailed(String),
+    Unloaded(String),
+    UnloadingFailed(String),
+}
+
+impl std::fmt::Display for ModelStatus {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(&match self {
+            Self::Loading => t!("model-loading"),
+            Self::Loaded(model) => {
+                t!("model-loaded", model = model)
+            }
+            Self::LoadingFailed(err) => {
+                t!("model-loading-failed", error = err)
+            }
+            Self::Unloaded(model) => {
+                t!("model-unloaded", model = model)
+            }
+            Self::UnloadingFailed(err) => {
+                t!("model-unloading-failed", error = err)
+            }
+        })
+    }
+}
+
+/// Load the configured model with an empty conversation, so
+/// the first prompt does not wait for it
+pub async fn warm_up(
+    settings: ApplicationSettings,
+) -> Result<(), String> {
+    api::chat(&chat_request(vec![], &settings))
+        .await
+        .map(|_| ())
         .map_err(|err| err.to_string())
 }
 
-pub async fn check_ai_health() -> bool {
-    let ollama = OLLAMA.lock().await;
-    ollama.list_local_models().await.is_ok()
+/// Free the memory held by the configured model
+pub async fn unload_model(
+    settings: ApplicationSettings,
+) -> Result<(), String> {
+    let mut request = chat_request(vec![], &settings);
+    request.keep_alive = Some(json!(0));
+
+    api::chat(&request)
+        .await
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Answer with the given model instead of the configured one,
+/// without tools. Requests are not serialized, so several
+/// models can be asked at once
+pub async fn ask_model(
+    mut messages: Vec<ChatMessage>,
+    model: String,
+    settings: ApplicationSettings,
+) -> Result<ModelAnswer, String> {
+    if let Some(instruction) =
+        language_instruction(&settings)
+    {
+        let prompt = messages.iter().rposition(|message| {
+            message.role == MessageRole::User
+        });
+        messages.insert(
+            prompt.unwrap_or(messages.len()),
+            instruction,
+        );
+    }
+
+    let request = chat_request(
+        messages
+            .into_iter()
+            .map(ApiMessage::from)
+            .collect(),
+        &ApplicationSettings {
+            ai_model: model,
+            ..settings
+        },
+    );
+
+    let started = Instant::now();
+    let response = api::chat(&request)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(ModelAnswer {
+        content: response.message.content,
+        latency: started.elapsed(),
+        stats: response.stats,
+    })
+}
+
+/// Ask for replies in the language of the interface when
+/// the settings say so
+fn language_instruction(
+    settings: &ApplicationSettings,
+) -> Option<ChatMessage> {
+    settings.reply_in_ui_language.then(|| {
+        ChatMessage::system(format!(
+            "Always answer in {}.",
+            i18n::current_language().english_name()
+        ))
+    })
+}
+
+async fn chat(
+    messages: Vec<ChatMessage>,
+    settings: ApplicationSettings,
+) -> Result<String, String> {
+    let request = chat_request(
+        messages
+            .into_iter()
+            .map(ApiMessage::from)
+            .collect(),
+        &settings,
+    );
+
+    api::chat(&request)
+        .await
+        .map(|response| response.message.content)
+        .map_err(|err| err.to_string())
 }
 
 pub async fn get_ai_models_installed(
@@ -53,3 +517,91 @@ pub async fn get_ai_models_installed(
         })
         .map_err(|err| err.to_string())
 }
+
+/// Ask the AI for a short title summing up the first prompt
+/// of a conversation
+pub async fn generate_title(
+    prompt: String,
+    settings: ApplicationSettings,
+) -> Result<String, String> {
+    let request = format!(
+        "Write a title of at most 6 words for a conversation \
+         starting with the message below. Answer with the \
+         title only.\n\n{prompt}"
+    );
+
+    chat(vec![ChatMessage::user(request)], settings)
+        .await
+        .map(|title| {
+            title
+                .lines()
+                .find(|line| !line.trim().is_empty())
+                .unwrap_or_default()
+                .trim()
+                .trim_matches(['"', '*', '#'])
+                .to_string()
+        })
+}
+
+/// Condense earlier messages into a note that replaces them
+/// in the context sent to the AI
+pub async fn summarize_messages(
+    messages: Vec<ChatMessage>,
+    settings: ApplicationSettings,
+) -> Result<String, String> {
+    let transcript = messages
+        .iter()
+        .map(|message| {
+            let role = match message.role {
+                MessageRole::System => "Earlier summary",
+                MessageRole::User => "User",
+                MessageRole::Assistant => "Assistant",
+            };
+            format!("{role}: {}", message.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let request = format!(
+        "{transcript}\n\nSummarize the conversation above into a \
+         compact note, keeping facts, decisions, names and code \
+         the user may refer to later. Answer with the note only."
+    );
+
+    chat(vec![ChatMessage::user(request)], settings)
+        .await
+        .map(|summary| summary.trim().to_string())
+}
+
+/// Context window defined by the model file, if any
+pub async fn get_model_context_length(
+    model: String,
+) -> Option<u32> {
+    let ollama = OLLAMA.lock().await;
+    let info = ollama.show_model_info(model).await.ok()?;
+
+    info.parameters.lines().find_map(|line| {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["num_ctx", value] => value.parse().ok(),
+            _ => None,
+        }
+    })
+}
+
+/// Embedding of a text, for documents search
+pub async fn embed(
+    text: String,
+    model: &str,
+) -> Result<Vec<f32>, String> {
+    let ollama = OLLAMA.lock().await;
+    ollama
+        .generate_embeddings(model.to_string(), text, None)
+        .await
+        .map(|res| {
+            res.embeddings
+                .into_iter()
+                .map(|v| v as f32)
+                .collect()
+        })
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use ollama_rs::generation::chat::{
+    ChatMessage, MessageRole,
+};
+use serde_json::Value;
+
+use crate::{debug, t, usage::GenerationStats};
+
+/// Address of the local Ollama server
+pub const OLLAMA_URL: &str = "http://127.0.0.1:11434";
+/// Limit for requests that do not run the model
+const QUICK_REQUEST_TIMEOUT: Duration =
+    Duration::from_secs(5);
+
+lazy_static! {
+    /// Client for the requests ollama-rs has no support for,
+    /// shared so connections are reused
+    static ref CLIENT: reqwest::Client =
+        reqwest::Client::new();
+}
+
+/// Message as sent to and received from `/api/chat`
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Default,
+)]
+pub struct ApiMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub tool_calls: Vec<ToolCall>,
+    /// Tool a `tool` message holds the result of
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tool_name: Option<String>,
+}
+
+impl ApiMessage {
+    pub fn tool(name: String, content: String) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content,
+            tool_calls: vec![],
+            tool_name: Some(name),
+        }
+    }
+}
+
+impl From<ChatMessage> for ApiMessage {
+    fn from(message: ChatMessage) -> Self {
+        let role = match message.role {
+            MessageRole::System => "system",
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+        };
+
+        Self {
+            role: role.to_string(),
+            content: message.content,
+            tool_calls: vec![],
+            tool_name: None,
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct ToolCall {
+    pub function: FunctionCall,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct FunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ApiMessage>,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Value>,
+    /// Definitions of the functions the model may call
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Value>,
+    /// `"json"`, or a JSON schema the answer has to match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Value>,
+    /// How long the model stays loaded after the request,
+    /// as seconds or a duration such as `"10m"`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<Value>,
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+}
+
+impl ChatRequest {
+    pub fn new(
+        model: String,
+        messages: Vec<ApiMessage>,
+    ) -> Self {
+        Self {
+            model,
+            messages,
+            stream: false,
+            options: None,
+            tools: vec![],
+            format: None,
+            keep_alive: None,
+            timeout: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ChatResponse {
+    /// Missing when only loading or unloading the model
+    #[serde(default)]
+    pub message: ApiMessage,
+    #[serde(flatten)]
+    pub stats: GenerationStats,
+}
+
+#[derive(serde::Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Send a conversation to `/api/chat` and wait for the whole
+/// answer
+pub async fn chat(
+    request: &ChatRequest,
+) -> Result<ChatResponse, AiError> {
+    debug!(&request.messages);
+
+    let mut builder = CLIENT
+        .post(format!("{OLLAMA_URL}/api/chat"))
+        .json(request);
+    if let Some(timeout) = request.timeout {
+        builder = builder.timeout(timeout);
+    }
+    let response =
+        builder.send().await.map_err(AiError::from)?;
+
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        let message = response
+            .json::<ErrorResponse>()
+            .await
+            .map(|body| body.error)
+            .unwrap_or_default();
+        return Err(AiError::from_status(
+            status,
+            message,
+            &request.model,
+        ));
+    }
+
+    response
+        .json::<ChatResponse>()
+        .await
+        .map_err(AiError::from)
+}
+
+/// Failure of a request to Ollama, sorted by what the user
+/// can do about it
+#[derive(Debug, Clone)]
+pub enum AiError {
+    /// The server could not be reached
+    ServerDown(String),
+    Timeout,
+    ModelNotFound(String),
+    OutOfMemory(String),
+    /// Other error status returned by the server
+    Server(u16, String),
+    Other(String),
+}
+
+impl AiError {
+    fn from_status(
+        status: u16,
+        message: String,
+        model: &str,
+    ) -> Self {
+        let lowercase = message.to_lowercase();
+
+        if lowercase.contains("out of memory")
+            || lowercase
+                .contains("requires more system memory")
+        {
+            Self::OutOfMemory(message)
+        } else if status == 404
+            || lowercase.contains("not found")
+        {
+            Self::ModelNotFound(model.to_string())
+        } else {
+            Self::Server(status, message)
+        }
+    }
+
+    /// Connection failures and server side errors may go
+    /// away by themselves, so the request is worth resending
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::ServerDown(_)
+                | Self::Server(500..=599, _)
+        )
+    }
+}
+
+/// Delay before the given try again of a failing request,
+/// doubling from `first` up to `max`
+pub fn backoff_delay(
+    attempt: u32,
+    first: Duration,
+    max: Duration,
+) -> Duration {
+    first
+        .saturating_mul(
+            1 << attempt.saturating_sub(1).min(16),
+        )
+        .min(max)
+}
+
+impl From<reqwest::Error> for AiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_connect() {
+            Self::ServerDown(err.to_string())
+        } else {
+            Self::Other(err.to_string())
+        }
+    }
+}
+
+impl From<String> for AiError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl std::fmt::Display for AiError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::ServerDown(err) => f.write_str(&t!(
+                "error-server-down",
+                error = err
+            )),
+            Self::Timeout => {
+                f.write_str(&t!("error-timeout"))
+            }
+            Self::ModelNotFound(model) => f.write_str(&t!(
+                "error-model-not-found",
+                model = model
+            )),
+            Self::OutOfMemory(err) => f.write_str(&t!(
+                "error-out-of-memory",
+                error = err
+            )),
+            Self::Server(status, err) => f.write_str(&t!(
+                "error-server",
+                status = *status,
+                error = err
+            )),
+            Self::Other(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// Model currently loaded in memory, as listed by `/api/ps`
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RunningModel {
+    pub name: String,
+    /// Bytes of the model held in video memory
+    #[serde(default)]
+    pub size_vram: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+#[derive(serde::Deserialize)]
+struct RunningModelsResponse {
+    #[serde(default)]
+    models: Vec<RunningModel>,
+}
+
+/// Step of a model download reported by `/api/pull`
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PullStatus {
+    pub status: String,
+    /// Bytes of the layer being downloaded
+    pub total: u64,
+    pub completed: u64,
+    pub error: Option<String>,
+}
+
+/// Download a model, reporting each step as it comes
+pub async fn pull(
+    model: &str,
+    mut on_status: impl FnMut(PullStatus),
+) -> Result<(), String> {
+    let mut response = CLIENT
+        .post(format!("{OLLAMA_URL}/api/pull"))
+        .json(&serde_json::json!({ "name": model, "stream": true }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?;
+
+    // Statuses come as JSON lines, possibly split across
+    // chunks
+    let mut buffer = vec![];
+    while let Some(chunk) =
+        response.chunk().await.map_err(|e| e.to_string())?
+    {
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) =
+            buffer.iter().position(|b| *b == b'\n')
+        {
+            let line =
+                buffer.drain(..=end).collect::<Vec<_>>();
+            let Ok(status) =
+                serde_json::from_slice::<PullStatus>(&line)
+            else {
+                continue;
+            };
+            if let Some(err) = status.error {
+                return Err(err);
+            }
+            on_status(status);
+        }
+    }
+
+    Ok(())
+}
+
+/// Version of the Ollama server, also telling it is up
+pub async fn version() -> Result<String, String> {
+    get::<VersionResponse>("/api/version")
+        .await
+        .map(|response| response.version)
+}
+
+pub async fn running_models(
+) -> Result<Vec<RunningModel>, String> {
+    get::<RunningModelsResponse>("/api/ps")
+        .await
+        .map(|response| response.models)
+}
+
+async fn get<T: serde::de::DeserializeOwned>(
+    path: &str,
+) -> Result<T, String> {
+    CLIENT
+        .get(format!("{OLLAMA_URL}{path}"))
+        .timeout(QUICK_REQUEST_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| err.to_string())?
+        .json::<T>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let delay = |attempt| {
+            backoff_delay(
+                attempt,
+                Duration::from_secs(1),
+                Duration::from_secs(30),
+            )
+            .as_secs()
+        };
+
+        assert_eq!(
+            (0..7).map(delay).collect::<Vec<_>>(),
+            [1, 1, 2, 4, 8, 16, 30]
+        );
+        assert_eq!(delay(u32::MAX), 30);
+    }
+}
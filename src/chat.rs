@@ -0,0 +1,744 @@
+use ollama_rs::generation::chat::ChatMessage;
+
+use crate::{
+    ai::AiReply, conversations::now, rag::Citation, t,
+    usage::GenerationStats,
+};
+
+/// Index of the empty root every conversation hangs from
+const ROOT: usize = 0;
+/// Tokens added by the chat template around each message
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Share of the context window above which older messages
+/// get summarized
+const SUMMARY_THRESHOLD_RATIO: f32 = 0.75;
+/// Share of the context window kept verbatim when older
+/// messages are summarized
+const KEPT_CONTEXT_RATIO: f32 = 0.3;
+/// Maximum number of previous messages sent with a prompt,
+/// in case older ones could not be summarized
+const HISTORY_LIMIT: usize = 30;
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+    /// Model that wrote the message, for replies
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Unix timestamp, 0 for messages stored before dates
+    /// were recorded
+    #[serde(default)]
+    pub created_at: u64,
+    /// Summary of every message preceding this one on its
+    /// branch, sent instead of them
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// Documents passages a reply was given
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub citations: Vec<Citation>,
+    /// Tool calls a reply was given the results of
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub tools_used: Vec<String>,
+    /// Reply given in JSON mode, shown as a tree
+    #[serde(
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub structured: bool,
+    /// Token counts and timings of a reply
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stats: Option<GenerationStats>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    selected_child: usize,
+}
+
+impl Message {
+    fn new(
+        role: Role,
+        content: String,
+        parent: Option<usize>,
+    ) -> Self {
+        Self {
+            role,
+            content,
+            model: None,
+            created_at: now(),
+            summary: None,
+            citations: vec![],
+            tools_used: vec![],
+            structured: false,
+            stats: None,
+            parent,
+            children: vec![],
+            selected_child: 0,
+        }
+    }
+
+    fn to_chat_message(&self) -> ChatMessage {
+        match self.role {
+            Role::System => {
+                ChatMessage::system(self.content.clone())
+            }
+            Role::User => {
+                ChatMessage::user(self.content.clone())
+            }
+            Role::Assistant => {
+                ChatMessage::assistant(self.content.clone())
+            }
+        }
+    }
+}
+
+/// Where a reply that is being generated will be attached
+/// once the AI answers
+#[derive(Debug, Clone)]
+pub struct PendingReply {
+    pub parent: usize,
+    pub prompt: Option<String>,
+}
+
+/// Conversation stored as a tree, so that regenerated replies
+/// and edited prompts are kept as alternate branches.
+/// The active branch is the path following `selected_child`
+/// from the root.
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct Conversation {
+    messages: Vec<Message>,
+}
+
+impl Default for Conversation {
+    fn default() -> Self {
+        Self {
+            messages: vec![Message::new(
+                Role::System,
+                String::new(),
+                None,
+            )],
+        }
+    }
+}
+
+impl Conversation {
+    pub fn message(&self, id: usize) -> &Message {
+        &self.messages[id]
+    }
+
+    /// Ids of the messages on the active branch, root excluded
+    pub fn active_path(&self) -> Vec<usize> {
+        let mut path = vec![];
+        let mut current = ROOT;
+
+        while let Some(&next) = self.messages[current]
+            .children
+            .get(self.messages[current].selected_child)
+        {
+            path.push(next);
+            current = next;
+        }
+
+        path
+    }
+
+    /// Last message of the active branch
+    pub fn tail(&self) -> usize {
+        self.active_path().last().copied().unwrap_or(ROOT)
+    }
+
+    /// Messages from the root to `id` included, ready to be
+    /// sent to the AI. Messages covered by a summary are
+    /// replaced by it
+    pub fn history_until(
+        &self,
+        id: usize,
+    ) -> Vec<ChatMessage> {
+        let (summary, mut path) = self.context_until(id);
+        path.drain(
+            ..path.len().saturating_sub(HISTORY_LIMIT),
+        );
+
+        summary
+            .map(|summary| {
+                ChatMessage::system(format!(
+                    "Summary of the earlier conversation:\n{summary}"
+                ))
+            })
+            .into_iter()
+            .chain(
+                path.into_iter()
+                    .map(|node| self.messages[node].to_chat_message()),
+            )
+            .collect()
+    }
+
+    /// Latest summary on the path to `id`, and the messages
+    /// following it up to `id` included
+    fn context_until(
+        &self,
+        id: usize,
+    ) -> (Option<&String>, Vec<usize>) {
+        let mut path = vec![];
+        let mut current = Some(id);
+
+        while let Some(node) =
+            current.filter(|&n| n != ROOT)
+        {
+            path.push(node);
+            if let Some(summary) =
+                &self.messages[node].summary
+            {
+                path.reverse();
+                return (Some(summary), path);
+            }
+            current = self.messages[node].parent;
+        }
+
+        path.reverse();
+        (None, path)
+    }
+
+    /// Approximate number of tokens sent along with the next
+    /// prompt, including the documents excerpts given with it
+    pub fn context_tokens(
+        &self,
+        excerpts_tokens: usize,
+    ) -> usize {
+        estimate_tokens(&self.history_until(self.tail()))
+            + excerpts_tokens
+    }
+
+    /// Whether the context is close enough to the limit for
+    /// older messages to be summarized
+    pub fn needs_summary(
+        &self,
+        context_length: u32,
+        excerpts_tokens: usize,
+    ) -> bool {
+        self.context_tokens(excerpts_tokens) as f32
+            > context_length as f32
+                * SUMMARY_THRESHOLD_RATIO
+    }
+
+    /// Messages of the active branch to fold into a summary so
+    /// that the context fits again in `context_length`, along
+    /// with the message the summary will be attached to
+    pub fn summary_candidates(
+        &self,
+        context_length: u32,
+    ) -> Option<(usize, Vec<ChatMessage>)> {
+        let (summary, path) =
+            self.context_until(self.tail());
+        let kept_budget = (context_length as f32
+            * KEPT_CONTEXT_RATIO)
+            as usize;
+
+        // Keep the latest messages within budget, always
+        // keeping at least the last exchange
+        let mut kept_tokens = 0;
+        let mut first_kept = path.len();
+        for (position, &node) in
+            path.iter().enumerate().rev()
+        {
+            kept_tokens += estimate_tokens(&[self
+                .messages[node]
+                .to_chat_message()]);
+            if kept_tokens > kept_budget
+                && path.len() - position > 2
+            {
+                break;
+            }
+            first_kept = position;
+        }
+
+        // Summaries start on a prompt so replies keep theirs
+        while first_kept > 0
+            && self.messages[path[first_kept]].role
+                != Role::User
+        {
+            first_kept -= 1;
+        }
+
+        if first_kept == 0 {
+            return None;
+        }
+
+        let to_summarize = summary
+            .map(|summary| {
+                ChatMessage::system(summary.clone())
+            })
+            .into_iter()
+            .chain(path[..first_kept].iter().map(|&node| {
+                self.messages[node].to_chat_message()
+            }))
+            .collect();
+
+        Some((path[first_kept], to_summarize))
+    }
+
+    pub fn set_summary(
+        &mut self,
+        id: usize,
+        summary: String,
+    ) {
+        if let Some(message) = self.messages.get_mut(id) {
+            message.summary = Some(summary);
+        }
+    }
+
+    /// Last reply of the active branch, if the branch ends
+    /// with one
+    pub fn last_reply(&self) -> Option<usize> {
+        let tail = self.tail();
+        (self.messages[tail].role == Role::Assistant)
+            .then_some(tail)
+    }
+
+    /// Last prompt written by the user on the active branch
+    pub fn last_prompt(&self) -> Option<usize> {
+        self.active_path().into_iter().rev().find(|&id| {
+            self.messages[id].role == Role::User
+        })
+    }
+
+    /// Reply to generate again from the prompt preceding the
+    /// last reply
+    pub fn regenerate(&self) -> Option<PendingReply> {
+        let reply = self.last_reply()?;
+        self.messages[reply].parent.map(|parent| {
+            PendingReply { parent, prompt: None }
+        })
+    }
+
+    /// Reply to a new prompt at the end of the active branch
+    pub fn follow_up(
+        &self,
+        prompt: String,
+    ) -> PendingReply {
+        PendingReply {
+            parent: self.tail(),
+            prompt: Some(prompt),
+        }
+    }
+
+    /// Reply to a rewritten version of the last prompt, which
+    /// will be added next to the original one
+    pub fn edit_last_prompt(
+        &self,
+        prompt: String,
+    ) -> Option<PendingReply> {
+        let last_prompt = self.last_prompt()?;
+        self.messages[last_prompt].parent.map(|parent| {
+            PendingReply { parent, prompt: Some(prompt) }
+        })
+    }
+
+    /// Messages to send to the AI for a pending reply
+    pub fn request_history(
+        &self,
+        pending: &PendingReply,
+    ) -> Vec<ChatMessage> {
+        let mut history =
+            self.history_until(pending.parent);
+        if let Some(prompt) = &pending.prompt {
+            history.push(ChatMessage::user(prompt.clone()));
+        }
+        history
+    }
+
+    /// Store the AI reply as the newly selected branch
+    pub fn attach_reply(
+        &mut self,
+        pending: PendingReply,
+        reply: AiReply,
+    ) -> usize {
+        let parent = match pending.prompt {
+            Some(prompt) => self.add_child(
+                pending.parent,
+                Role::User,
+                prompt,
+            ),
+            None => pending.parent,
+        };
+
+        let id = self.add_child(
+            parent,
+            Role::Assistant,
+            reply.content,
+        );
+        self.messages[id].model = Some(reply.model);
+        self.messages[id].citations = reply.citations;
+        self.messages[id].tools_used = reply.tools_used;
+        self.messages[id].structured = reply.structured;
+        self.messages[id].stats = Some(reply.stats);
+
+        id
+    }
+
+    /// Position of the message among its alternatives and
+    /// how many of them exist, both 1-based for display
+    pub fn branch_position(
+        &self,
+        id: usize,
+    ) -> (usize, usize) {
+        match self.messages[id].parent {
+            Some(parent) => {
+                let parent = &self.messages[parent];
+                (
+                    parent.selected_child + 1,
+                    parent.children.len(),
+                )
+            }
+            None => (1, 1),
+        }
+    }
+
+    /// Switch to the previous or next alternative of a message
+    pub fn select_sibling(
+        &mut self,
+        id: usize,
+        forward: bool,
+    ) {
+        let Some(parent) = self.messages[id].parent else {
+            return;
+        };
+        let parent = &mut self.messages[parent];
+        let count = parent.children.len();
+
+        parent.selected_child = if forward {
+            (parent.selected_child + 1).min(count - 1)
+        } else {
+            parent.selected_child.saturating_sub(1)
+        };
+    }
+
+    /// Select every branch leading to a message so that it
+    /// becomes part of the active branch
+    pub fn reveal(&mut self, id: usize) {
+        let mut current = id;
+
+        while let Some(parent) = self
+            .messages
+            .get(current)
+            .and_then(|message| message.parent)
+        {
+            let parent_message = &mut self.messages[parent];
+            if let Some(position) = parent_message
+                .children
+                .iter()
+                .position(|&child| child == current)
+            {
+                parent_message.selected_child = position;
+            }
+            current = parent;
+        }
+    }
+
+    /// Every message stored, including the ones of inactive
+    /// branches, root excluded
+    pub fn all_messages(
+        &self,
+    ) -> impl Iterator<Item = (usize, &Message)> {
+        self.messages.iter().enumerate().skip(1)
+    }
+
+    /// Append a message at the end of the active branch
+    pub fn push(
+        &mut self,
+        role: Role,
+        content: String,
+    ) -> usize {
+        self.add_child(self.tail(), role, content)
+    }
+
+    /// Check that the message tree read from a file is
+    /// consistent
+    pub fn validate(&self) -> Result<(), String> {
+        let root = self
+            .messages
+            .first()
+            .ok_or_else(|| t!("conversation-no-root"))?;
+        if root.parent.is_some() {
+            return Err(t!("conversation-root-has-parent"));
+        }
+
+        for (id, message) in
+            self.messages.iter().enumerate()
+        {
+            if message.parent.is_some_and(|parent| {
+                !self.messages.get(parent).is_some_and(
+                    |p| p.children.contains(&id),
+                )
+            }) {
+                return Err(t!(
+                    "conversation-orphan-message",
+                    id = id
+                ));
+            }
+            if message.children.iter().any(|&child| {
+                self.messages
+                    .get(child)
+                    .is_none_or(|c| c.parent != Some(id))
+            }) {
+                return Err(t!(
+                    "conversation-invalid-child",
+                    id = id
+                ));
+            }
+            if !message.children.is_empty()
+                && message.selected_child
+                    >= message.children.len()
+            {
+                return Err(t!(
+                    "conversation-missing-branch",
+                    id = id
+                ));
+            }
+        }
+
+        // Links agreeing both ways can still form a loop, which
+        // walking up or down the tree would never leave
+        let mut reached = vec![false; self.messages.len()];
+        let mut next = vec![ROOT];
+        while let Some(id) = next.pop() {
+            if std::mem::replace(&mut reached[id], true) {
+                return Err(t!(
+                    "conversation-cycle",
+                    id = id
+                ));
+            }
+            next.extend(&self.messages[id].children);
+        }
+        if let Some(id) =
+            reached.iter().position(|reached| !reached)
+        {
+            return Err(t!(
+                "conversation-unreachable-message",
+                id = id
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn add_child(
+        &mut self,
+        parent: usize,
+        role: Role,
+        content: String,
+    ) -> usize {
+        let id = self.messages.len();
+        self.messages.push(Message::new(
+            role,
+            content,
+            Some(parent),
+        ));
+
+        let parent = &mut self.messages[parent];
+        parent.children.push(id);
+        parent.selected_child = parent.children.len() - 1;
+
+        id
+    }
+}
+
+/// Rough number of tokens of messages, counting about four
+/// characters per token
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            message.content.chars().count() / 4
+                + MESSAGE_OVERHEAD_TOKENS
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(content: &str) -> AiReply {
+        AiReply {
+            content: content.into(),
+            model: "llama3".into(),
+            citations: vec![],
+            tools_used: vec![],
+            structured: false,
+            stats: GenerationStats::default(),
+            documents_error: None,
+        }
+    }
+
+    /// Conversation with one prompt and its reply
+    fn exchange() -> Conversation {
+        let mut conversation = Conversation::default();
+        let pending =
+            conversation.follow_up("Hello".into());
+        conversation.attach_reply(pending, reply("Hi"));
+        conversation
+    }
+
+    fn contents(conversation: &Conversation) -> Vec<&str> {
+        conversation
+            .active_path()
+            .into_iter()
+            .map(|id| {
+                conversation.message(id).content.as_str()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn follow_up_extends_the_active_branch() {
+        let mut conversation = exchange();
+        let pending =
+            conversation.follow_up("And then?".into());
+        conversation.attach_reply(pending, reply("Done"));
+
+        assert_eq!(
+            contents(&conversation),
+            ["Hello", "Hi", "And then?", "Done"]
+        );
+        assert!(conversation.validate().is_ok());
+    }
+
+    #[test]
+    fn regenerate_adds_a_sibling_reply() {
+        let mut conversation = exchange();
+        let first = conversation.last_reply().unwrap();
+        let pending = conversation.regenerate().unwrap();
+        assert_eq!(
+            conversation.request_history(&pending).len(),
+            1
+        );
+
+        let second = conversation
+            .attach_reply(pending, reply("Hey"));
+
+        assert_eq!(
+            contents(&conversation),
+            ["Hello", "Hey"]
+        );
+        assert_eq!(
+            conversation.branch_position(second),
+            (2, 2)
+        );
+
+        conversation.select_sibling(second, false);
+        assert_eq!(conversation.tail(), first);
+        assert_eq!(
+            conversation.branch_position(first),
+            (1, 2)
+        );
+        assert!(conversation.validate().is_ok());
+    }
+
+    #[test]
+    fn editing_the_prompt_keeps_the_original_branch() {
+        let mut conversation = exchange();
+        let original = conversation.last_prompt().unwrap();
+        let pending = conversation
+            .edit_last_prompt("Good morning".into())
+            .unwrap();
+        conversation
+            .attach_reply(pending, reply("Morning"));
+
+        assert_eq!(
+            contents(&conversation),
+            ["Good morning", "Morning"]
+        );
+
+        conversation.reveal(original);
+        assert_eq!(
+            contents(&conversation),
+            ["Hello", "Hi"]
+        );
+        assert_eq!(conversation.all_messages().count(), 4);
+        assert!(conversation.validate().is_ok());
+    }
+
+    #[test]
+    fn select_sibling_stays_within_the_branches() {
+        let mut conversation = exchange();
+        let reply_id = conversation.last_reply().unwrap();
+
+        conversation.select_sibling(reply_id, true);
+        assert_eq!(conversation.tail(), reply_id);
+        conversation.select_sibling(reply_id, false);
+        assert_eq!(conversation.tail(), reply_id);
+    }
+
+    #[test]
+    fn nothing_to_regenerate_or_edit_when_empty() {
+        let conversation = Conversation::default();
+
+        assert!(conversation.regenerate().is_none());
+        assert!(conversation
+            .edit_last_prompt("Hello".into())
+            .is_none());
+        assert!(conversation.active_path().is_empty());
+    }
+
+    #[test]
+    fn summary_replaces_the_messages_before_it() {
+        let mut conversation = exchange();
+        let pending =
+            conversation.follow_up("And then?".into());
+        let last = conversation
+            .attach_reply(pending, reply("Done"));
+        let prompt = conversation.last_prompt().unwrap();
+        conversation
+            .set_summary(prompt, "Greetings".into());
+
+        let history = conversation.history_until(last);
+        assert_eq!(history.len(), 3);
+        assert!(history[0].content.contains("Greetings"));
+        assert_eq!(history[1].content, "And then?");
+    }
+
+    #[test]
+    fn history_is_capped_without_a_summary() {
+        let mut conversation = Conversation::default();
+        for i in 0..HISTORY_LIMIT + 10 {
+            conversation.push(Role::User, i.to_string());
+        }
+
+        let history =
+            conversation.history_until(conversation.tail());
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(
+            history[HISTORY_LIMIT - 1].content,
+            (HISTORY_LIMIT + 9).to_string()
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::path::PathBuf;
+
+use crate::{
+    config,
+    conversations::{
+        load_conversations, save_conversations,
+    },
+    export::{export_to_file, ExportFormat},
+    import::import_file,
+};
+
+const USAGE: &str = "Usage:
+  ai-overlay                    Open the overlay
+  ai-overlay list               List saved conversations
+  ai-overlay export [ID] [--format md|json|html] [--output PATH]
+                                Export a conversation, the selected one by default
+  ai-overlay import PATH        Import a JSON transcript or an OpenAI `messages` array";
+
+/// Commands run from the terminal instead of opening the
+/// overlay
+#[derive(Debug, Clone)]
+pub enum CliCommand {
+    /// Print the usage
+    Help,
+    List,
+    Export {
+        id: Option<u64>,
+        format: ExportFormat,
+        output: Option<PathBuf>,
+    },
+    Import(PathBuf),
+}
+
+/// Parse the command line, `None` meaning the overlay
+/// should be opened
+pub fn parse_args(
+    args: &[String],
+) -> Result<Option<CliCommand>, String> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+
+    match command.as_str() {
+        "list" => Ok(Some(CliCommand::List)),
+        "export" => parse_export(rest).map(Some),
+        "import" => match rest {
+            [path] => Ok(Some(CliCommand::Import(
+                PathBuf::from(path),
+            ))),
+            _ => {
+                Err(format!("Expected one path\n\n{USAGE}"))
+            }
+        },
+        "-h" | "--help" | "help" => {
+            Ok(Some(CliCommand::Help))
+        }
+        other => Err(format!(
+            "Unknown command `{other}`\n\n{USAGE}"
+        )),
+    }
+}
+
+fn parse_export(
+    args: &[String],
+) -> Result<CliCommand, String> {
+    let mut id = None;
+    let mut format = ExportFormat::Markdown;
+    let mut output = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => {
+                format = args
+                    .next()
+                    .ok_or("Missing value for --format")?
+                    .parse()?;
+            }
+            "--output" | "-o" => {
+                output = Some(PathBuf::from(
+                    args.next().ok_or(
+                        "Missing value for --output",
+                    )?,
+                ));
+            }
+            value => {
+                id = Some(value.parse::<u64>().map_err(|_| {
+                    format!("Invalid conversation id `{value}`")
+                })?);
+            }
+        }
+    }
+
+    Ok(CliCommand::Export { id, format, output })
+}
+
+/// Run a command, returning what should be printed
+pub fn run(command: CliCommand) -> Result<String, String> {
+    let mut store = load_conversations();
+
+    match command {
+        CliCommand::Help => Ok(USAGE.to_string()),
+        CliCommand::List => Ok(store
+            .sorted()
+            .into_iter()
+            .map(|saved| {
+                format!(
+                    "{}{}\t{}",
+                    saved.id,
+                    if saved.pinned { "*" } else { "" },
+                    saved.title
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        CliCommand::Export { id, format, output } => {
+            let saved = match id {
+                Some(id) => store.get(id).ok_or(
+                    format!("No conversation with id {id}"),
+                )?,
+                None => store.selected(),
+            };
+            let settings = config::load_settings();
+
+            export_to_file(saved, &settings, format, output)
+                .map(|path| {
+                    format!(
+                        "Exported \"{}\" to {}",
+                        saved.title,
+                        path.display()
+                    )
+                })
+        }
+        CliCommand::Import(path) => {
+            let report = import_file(&path, &mut store)?;
+            save_conversations(&store)?;
+            Ok(report.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(
+        args: &[&str],
+    ) -> Result<Option<CliCommand>, String> {
+        parse_args(
+            &args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn help_is_a_command() {
+        for flag in ["-h", "--help", "help"] {
+            assert!(matches!(
+                parse(&[flag]),
+                Ok(Some(CliCommand::Help))
+            ));
+        }
+        assert!(matches!(parse(&[]), Ok(None)));
+        assert!(parse(&["unknown"]).is_err());
+    }
+
+    #[test]
+    fn parses_export_options() {
+        let Ok(Some(CliCommand::Export {
+            id,
+            format,
+            output,
+        })) = parse(&[
+            "export", "3", "-f", "html", "-o", "out.html",
+        ])
+        else {
+            panic!("export was not parsed");
+        };
+
+        assert_eq!(id, Some(3));
+        assert_eq!(format, ExportFormat::Html);
+        assert_eq!(output, Some(PathBuf::from("out.html")));
+        assert!(parse(&["export", "--format"]).is_err());
+        assert!(parse(&["export", "latest"]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{ai::ModelAnswer, chat::PendingReply};
+
+/// Answer of one model of a comparison, `None` until it
+/// arrives
+#[derive(Debug, Clone)]
+pub struct ComparedAnswer {
+    pub model: String,
+    pub result: Option<Result<ModelAnswer, String>>,
+}
+
+/// One prompt sent to several models at once, any of the
+/// answers being promotable into the conversation
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    pub prompt: String,
+    pub models: Vec<String>,
+    /// Incremented for each run so late answers of a previous
+    /// one are ignored
+    pub run: u64,
+    /// Conversation and position the prompt was asked from
+    pub target: Option<(u64, PendingReply)>,
+    pub answers: Vec<ComparedAnswer>,
+}
+
+impl Comparison {
+    pub fn toggle_model(
+        &mut self,
+        model: String,
+        on: bool,
+    ) {
+        self.models.retain(|m| *m != model);
+        if on {
+            self.models.push(model);
+        }
+    }
+
+    /// Reset the answers for a new run of the selected models
+    pub fn start(
+        &mut self,
+        conversation: u64,
+        pending: PendingReply,
+    ) -> u64 {
+        self.run += 1;
+        self.target = Some((conversation, pending));
+        self.answers = self
+            .models
+            .iter()
+            .map(|model| ComparedAnswer {
+                model: model.clone(),
+                result: None,
+            })
+            .collect();
+        self.run
+    }
+
+    pub fn set_answer(
+        &mut self,
+        run: u64,
+        index: usize,
+        result: Result<ModelAnswer, String>,
+    ) {
+        if run != self.run {
+            return;
+        }
+        if let Some(answer) = self.answers.get_mut(index) {
+            answer.result = Some(result);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.answers.iter().any(|a| a.result.is_none())
+    }
+
+    /// Answer to move into the conversation, the comparison
+    /// being over once one is picked
+    pub fn take_answer(
+        &mut self,
+        index: usize,
+    ) -> Option<(u64, PendingReply, String, ModelAnswer)>
+    {
+        let answer = self.answers.get(index)?.clone();
+        let Some(Ok(result)) = answer.result else {
+            return None;
+        };
+        let (conversation, pending) = self.target.take()?;
+        self.answers.clear();
+
+        Some((conversation, pending, answer.model, result))
+    }
+}
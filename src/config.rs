@@ -1,19 +1,139 @@
-use std::{fs::OpenOptions, io::Read};
+use std::{
+    collections::BTreeMap, fs::OpenOptions, io::Read,
+};
 
-use crate::debug;
+use crate::{
+    debug, fonts,
+    geometry::{WindowGeometry, WindowPlacement},
+    i18n::Language,
+};
 
 const APP_CONFIG_FILE_NAME: &str = "settings.json";
 
 #[derive(
     serde::Deserialize, serde::Serialize, Debug, Clone,
 )]
+#[serde(default)]
 pub struct ApplicationSettings {
     pub ai_model: String,
+    /// Context window override, the model's own one is used
+    /// when unset
+    pub num_ctx: Option<u32>,
+    /// Folder of text and Markdown files answers can draw
+    /// from
+    pub documents_dir: Option<String>,
+    pub embedding_model: String,
+    /// Number of documents passages sent with a prompt
+    pub documents_top_k: usize,
+    /// Let the AI call the built-in tools, each call being
+    /// approved first
+    pub tools_enabled: bool,
+    /// Folder the file tools and commands are confined to
+    pub workspace_dir: Option<String>,
+    /// Commands the AI may run, each with the exact
+    /// arguments written here
+    pub allowed_commands: Vec<String>,
+    /// Make every reply a JSON document
+    pub json_mode: bool,
+    /// Schema JSON replies are validated against, inline or
+    /// as the path of a file
+    pub json_schema: Option<String>,
+    /// Seconds a reply may take before the request is
+    /// abandoned
+    pub request_timeout: u64,
+    /// Times a reply is asked again when Ollama cannot be
+    /// reached or fails
+    pub max_retries: u32,
+    /// How long Ollama keeps the model loaded once idle, as
+    /// seconds or a duration like `10m`, `-1` for ever. The
+    /// server default applies when empty
+    pub keep_alive: String,
+    /// Name of the theme to draw with, the one matching the
+    /// system color scheme being used when unset
+    pub theme: Option<String>,
+    /// Opacity of the overlay background from 0 to 1, the
+    /// theme's one when unset
+    pub background_opacity: Option<f32>,
+    /// Color of the overlay border as `#rrggbb`, the theme's
+    /// one when unset
+    pub border_color: Option<String>,
+    /// Corner radius of the overlay, the theme's one when
+    /// unset
+    pub corner_radius: Option<f32>,
+    /// White on black colors with yellow highlights, over
+    /// any theme
+    pub high_contrast: bool,
+    /// Last geometry of the window on each monitor
+    pub window_geometry: BTreeMap<String, WindowGeometry>,
+    /// Monitor the window was on last
+    pub last_monitor: Option<String>,
+    /// Hide the overlay when it is closed instead of
+    /// quitting, so it shows again instantly. Off by
+    /// default, closing it or pressing Escape then quits
+    pub hide_on_close: bool,
+    /// Hide the overlay when another window is focused,
+    /// off by default
+    pub hide_on_focus_loss: bool,
+    /// Keep the overlay above the other windows, off by
+    /// default
+    pub always_on_top: bool,
+    /// Where the overlay shows up when opened
+    pub placement: WindowPlacement,
+    /// Language of the interface, the one of the
+    /// environment when unset
+    pub language: Option<Language>,
+    /// Tell the model to answer in the interface language
+    pub reply_in_ui_language: bool,
+    /// Font family of the interface, applied on the next
+    /// start
+    pub ui_font: Option<String>,
+    /// Font family of the replies, the interface one when
+    /// unset
+    pub reply_font: Option<String>,
+    /// Font family of the code blocks of replies
+    pub code_font: Option<String>,
+    /// Scale of the whole interface
+    pub zoom: f64,
 }
 
 impl Default for ApplicationSettings {
     fn default() -> Self {
-        Self { ai_model: "qwen:0.5b".to_string() }
+        Self {
+            ai_model: "qwen:0.5b".to_string(),
+            num_ctx: None,
+            documents_dir: None,
+            embedding_model: "nomic-embed-text".to_string(),
+            documents_top_k: 4,
+            tools_enabled: false,
+            workspace_dir: None,
+            allowed_commands: vec![
+                "git status".to_string(),
+                "git log".to_string(),
+                "uname -a".to_string(),
+            ],
+            json_mode: false,
+            json_schema: None,
+            request_timeout: 300,
+            max_retries: 3,
+            keep_alive: "10m".to_string(),
+            theme: None,
+            background_opacity: None,
+            border_color: None,
+            corner_radius: None,
+            high_contrast: false,
+            window_geometry: BTreeMap::new(),
+            last_monitor: None,
+            hide_on_close: false,
+            hide_on_focus_loss: false,
+            always_on_top: false,
+            placement: WindowPlacement::default(),
+            language: None,
+            reply_in_ui_language: false,
+            ui_font: None,
+            reply_font: None,
+            code_font: None,
+            zoom: fonts::DEFAULT_ZOOM,
+        }
     }
 }
 
@@ -22,6 +142,7 @@ pub fn load_settings() -> ApplicationSettings {
         .read(true)
         .write(true)
         .create(true)
+        .truncate(false)
         .open(APP_CONFIG_FILE_NAME)
         .unwrap();
     let mut contents = String::new();
@@ -35,9 +156,17 @@ pub fn load_settings() -> ApplicationSettings {
     settings
 }
 
-#[allow(dead_code)]
+/// Write the settings file, the previous settings staying
+/// in place when it cannot be written
 pub fn save_settings(settings: ApplicationSettings) {
-    let settings =
-        serde_json::to_string(&settings).unwrap();
-    std::fs::write(APP_CONFIG_FILE_NAME, settings).unwrap();
+    let result = serde_json::to_string(&settings)
+        .map_err(|err| err.to_string())
+        .and_then(|settings| {
+            std::fs::write(APP_CONFIG_FILE_NAME, settings)
+                .map_err(|err| err.to_string())
+        });
+
+    if let Err(err) = result {
+        debug!(err);
+    }
 }
@@ -0,0 +1,221 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{chat::Conversation, debug, t};
+
+const CONVERSATIONS_FILE_NAME: &str = "conversations.json";
+const DEFAULT_TITLE: &str = "New conversation";
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct SavedConversation {
+    pub id: u64,
+    pub title: String,
+    /// Whether the title was generated or set by the user
+    pub titled: bool,
+    pub pinned: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub conversation: Conversation,
+}
+
+impl SavedConversation {
+    /// Title in the interface language while the
+    /// conversation has not been named yet
+    pub fn display_title(&self) -> String {
+        if !self.titled && self.title == DEFAULT_TITLE {
+            t!("conversation-untitled")
+        } else {
+            self.title.clone()
+        }
+    }
+}
+
+/// Every conversation of the user, the selected one being
+/// the one prompts are sent to
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct ConversationStore {
+    conversations: Vec<SavedConversation>,
+    selected: u64,
+    next_id: u64,
+}
+
+impl Default for ConversationStore {
+    fn default() -> Self {
+        let mut store = Self {
+            conversations: vec![],
+            selected: 0,
+            next_id: 0,
+        };
+        store.create();
+        store
+    }
+}
+
+impl ConversationStore {
+    /// Start a new empty conversation and select it
+    pub fn create(&mut self) -> u64 {
+        self.insert(
+            DEFAULT_TITLE.to_string(),
+            false,
+            Conversation::default(),
+        )
+    }
+
+    /// Add an existing conversation under a new id and
+    /// select it
+    pub fn insert(
+        &mut self,
+        title: String,
+        titled: bool,
+        conversation: Conversation,
+    ) -> u64 {
+        let id = self.next_id;
+        let now = now();
+        self.next_id += 1;
+
+        self.conversations.push(SavedConversation {
+            id,
+            title,
+            titled,
+            pinned: false,
+            created_at: now,
+            updated_at: now,
+            conversation,
+        });
+        self.selected = id;
+
+        id
+    }
+
+    pub fn selected_id(&self) -> u64 {
+        self.selected
+    }
+
+    pub fn selected(&self) -> &SavedConversation {
+        self.conversations
+            .iter()
+            .find(|c| c.id == self.selected)
+            .unwrap_or(&self.conversations[0])
+    }
+
+    pub fn current(&self) -> &Conversation {
+        &self.selected().conversation
+    }
+
+    pub fn current_mut(&mut self) -> &mut Conversation {
+        let id = self.selected().id;
+        &mut self.get_mut(id).unwrap().conversation
+    }
+
+    pub fn get(
+        &self,
+        id: u64,
+    ) -> Option<&SavedConversation> {
+        self.conversations.iter().find(|c| c.id == id)
+    }
+
+    pub fn get_mut(
+        &mut self,
+        id: u64,
+    ) -> Option<&mut SavedConversation> {
+        self.conversations.iter_mut().find(|c| c.id == id)
+    }
+
+    pub fn select(&mut self, id: u64) {
+        if self.get(id).is_some() {
+            self.selected = id;
+        }
+    }
+
+    pub fn rename(&mut self, id: u64, title: String) {
+        let title = title.trim();
+        if let Some(saved) =
+            self.get_mut(id).filter(|_| !title.is_empty())
+        {
+            saved.title = title.to_string();
+            saved.titled = true;
+        }
+    }
+
+    pub fn toggle_pin(&mut self, id: u64) {
+        if let Some(saved) = self.get_mut(id) {
+            saved.pinned = !saved.pinned;
+        }
+    }
+
+    /// Remove a conversation, keeping at least an empty one
+    /// around to send prompts to
+    pub fn delete(&mut self, id: u64) {
+        self.conversations.retain(|c| c.id != id);
+
+        if self.conversations.is_empty() {
+            self.create();
+        } else if self.selected == id {
+            self.selected = self.sorted()[0].id;
+        }
+    }
+
+    /// Mark a conversation as just used
+    pub fn touch(&mut self, id: u64) {
+        if let Some(saved) = self.get_mut(id) {
+            saved.updated_at = now();
+        }
+    }
+
+    /// Conversations as listed in the sidebar, pinned first
+    /// then most recently used
+    pub fn sorted(&self) -> Vec<&SavedConversation> {
+        let mut conversations =
+            self.conversations.iter().collect::<Vec<_>>();
+        conversations.sort_by(|a, b| {
+            b.pinned
+                .cmp(&a.pinned)
+                .then(b.updated_at.cmp(&a.updated_at))
+        });
+        conversations
+    }
+}
+
+pub fn load_conversations() -> ConversationStore {
+    let store =
+        std::fs::read_to_string(CONVERSATIONS_FILE_NAME)
+            .ok()
+            .and_then(|contents| {
+                serde_json::from_str::<ConversationStore>(
+                    &contents,
+                )
+                .ok()
+            })
+            .filter(|store| !store.conversations.is_empty())
+            .unwrap_or_default();
+
+    debug!(store.conversations.len());
+
+    store
+}
+
+pub fn save_conversations(
+    store: &ConversationStore,
+) -> Result<(), String> {
+    let result = serde_json::to_string(store)
+        .map_err(|err| err.to_string())
+        .and_then(|store| {
+            std::fs::write(CONVERSATIONS_FILE_NAME, store)
+                .map_err(|err| err.to_string())
+        });
+
+    if let Err(err) = &result {
+        debug!(err);
+    }
+    result
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
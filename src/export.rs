@@ -0,0 +1,313 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::{
+    chat::Role,
+    config::ApplicationSettings,
+    conversations::{now, SavedConversation},
+    i18n, structured,
+};
+
+const EXPORTS_DIR_NAME: &str = "exports";
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [
+        ExportFormat::Markdown,
+        ExportFormat::Json,
+        ExportFormat::Html,
+    ];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            ExportFormat::Markdown => write!(f, "Markdown"),
+            ExportFormat::Json => write!(f, "JSON"),
+            ExportFormat::Html => write!(f, "HTML"),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "json" => Ok(ExportFormat::Json),
+            "html" => Ok(ExportFormat::Html),
+            other => Err(format!(
+                "Unknown export format `{other}`, expected md, json or html"
+            )),
+        }
+    }
+}
+
+/// Lossless export of a conversation, every branch included
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+pub struct Transcript {
+    pub version: u32,
+    pub exported_at: u64,
+    /// Model selected when the conversation was exported
+    pub model: String,
+    /// Context window override in use, if any
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+    /// Settings the replies were generated with
+    #[serde(default)]
+    pub parameters: GenerationParameters,
+    pub conversation: SavedConversation,
+}
+
+/// Settings shaping the replies besides the model and its
+/// context window
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Default,
+)]
+#[serde(default)]
+pub struct GenerationParameters {
+    pub json_mode: bool,
+    /// Schema JSON replies were validated against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_schema: Option<serde_json::Value>,
+    pub tools_enabled: bool,
+    /// Documents passages sent with each prompt, unset when
+    /// no documents folder was configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documents_top_k: Option<usize>,
+    pub embedding_model: String,
+    /// Language replies were asked to be written in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_language: Option<String>,
+}
+
+impl GenerationParameters {
+    fn from_settings(
+        settings: &ApplicationSettings,
+    ) -> Self {
+        Self {
+            json_mode: settings.json_mode,
+            json_schema: settings
+                .json_mode
+                .then(|| structured::load_schema(settings))
+                .and_then(|schema| schema.ok().flatten()),
+            tools_enabled: settings.tools_enabled,
+            documents_top_k: settings
+                .documents_dir
+                .as_ref()
+                .map(|_| settings.documents_top_k),
+            embedding_model: settings
+                .embedding_model
+                .clone(),
+            reply_language: settings
+                .reply_in_ui_language
+                .then(|| {
+                    i18n::current_language()
+                        .english_name()
+                        .to_string()
+                }),
+        }
+    }
+}
+
+pub fn export_conversation(
+    saved: &SavedConversation,
+    settings: &ApplicationSettings,
+    format: ExportFormat,
+) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(saved),
+        ExportFormat::Json => to_json(saved, settings),
+        ExportFormat::Html => to_html(saved),
+    }
+}
+
+/// Export a conversation into the exports folder, returning
+/// the path of the written file
+pub fn export_to_file(
+    saved: &SavedConversation,
+    settings: &ApplicationSettings,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let path = match output {
+        Some(path) => path,
+        None => {
+            std::fs::create_dir_all(EXPORTS_DIR_NAME)
+                .map_err(|err| err.to_string())?;
+            PathBuf::from(EXPORTS_DIR_NAME).join(format!(
+                "{}-{}.{}",
+                slug(&saved.display_title()),
+                saved.id,
+                format.extension()
+            ))
+        }
+    };
+
+    std::fs::write(
+        &path,
+        export_conversation(saved, settings, format),
+    )
+    .map_err(|err| err.to_string())?;
+
+    Ok(path)
+}
+
+fn to_markdown(saved: &SavedConversation) -> String {
+    let conversation = &saved.conversation;
+
+    conversation.active_path().into_iter().fold(
+        format!("# {}\n", saved.display_title()),
+        |mut markdown, id| {
+            let message = conversation.message(id);
+            let heading =
+                match (&message.role, &message.model) {
+                    (Role::Assistant, Some(model)) => {
+                        format!("Assistant ({model})")
+                    }
+                    (role, _) => {
+                        role_label(role).to_string()
+                    }
+                };
+
+            markdown.push_str(&format!(
+                "\n## {heading}\n\n{}\n",
+                message.content.trim_end()
+            ));
+            markdown
+        },
+    )
+}
+
+fn to_json(
+    saved: &SavedConversation,
+    settings: &ApplicationSettings,
+) -> String {
+    serde_json::to_string_pretty(&Transcript {
+        version: TRANSCRIPT_VERSION,
+        exported_at: now(),
+        model: settings.ai_model.clone(),
+        num_ctx: settings.num_ctx,
+        parameters: GenerationParameters::from_settings(
+            settings,
+        ),
+        conversation: saved.clone(),
+    })
+    .unwrap()
+}
+
+fn to_html(saved: &SavedConversation) -> String {
+    let conversation = &saved.conversation;
+    let messages = conversation
+        .active_path()
+        .into_iter()
+        .map(|id| {
+            let message = conversation.message(id);
+            format!(
+                "<section class=\"{}\">\n<h2>{}</h2>\n{}</section>\n",
+                role_label(&message.role).to_lowercase(),
+                role_label(&message.role),
+                content_to_html(&message.content)
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n\
+         body {{ font-family: sans-serif; max-width: 800px; margin: auto; }}\n\
+         section {{ border-radius: 8px; padding: 4px 16px; margin: 8px 0; }}\n\
+         .user {{ background: #eef; }}\n\
+         .assistant {{ background: #efe; }}\n\
+         pre {{ background: #222; color: #eee; padding: 8px; overflow-x: auto; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n{messages}</body>\n</html>\n",
+        title = escape_html(&saved.display_title()),
+    )
+}
+
+/// Paragraphs of the message, with fenced code blocks kept as
+/// preformatted code
+fn content_to_html(content: &str) -> String {
+    content
+        .split("```")
+        .enumerate()
+        .map(|(index, part)| {
+            if index % 2 == 1 {
+                // Fenced code, the first line being the language
+                let code = part
+                    .split_once('\n')
+                    .map(|(_, code)| code)
+                    .unwrap_or(part);
+                format!(
+                    "<pre><code>{}</code></pre>\n",
+                    escape_html(code)
+                )
+            } else {
+                part.split("\n\n")
+                    .filter(|p| !p.trim().is_empty())
+                    .map(|p| {
+                        format!(
+                            "<p>{}</p>\n",
+                            escape_html(p.trim())
+                                .replace('\n', "<br>")
+                        )
+                    })
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn role_label(role: &Role) -> &'static str {
+    match role {
+        Role::System => "System",
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+    }
+}
+
+fn slug(title: &str) -> String {
+    let slug = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if slug.is_empty() {
+        "conversation".to_string()
+    } else {
+        slug
+    }
+}
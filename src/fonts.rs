@@ -0,0 +1,163 @@
+use std::{process::Command, sync::RwLock};
+
+use iced::{font::Family, Font};
+
+use crate::config::ApplicationSettings;
+
+pub const DEFAULT_ZOOM: f64 = 1.0;
+pub const MIN_ZOOM: f64 = 0.5;
+pub const MAX_ZOOM: f64 = 3.0;
+/// Zoom change of each Ctrl+Plus or Ctrl+Minus
+pub const ZOOM_STEP: f64 = 0.1;
+
+/// Families every system has, offered before the installed
+/// ones
+pub const GENERIC_FAMILIES: [&str; 3] =
+    ["Sans-serif", "Serif", "Monospace"];
+
+/// Families of the fonts installed on the system
+static FAMILIES: RwLock<Vec<String>> = RwLock::new(vec![]);
+/// Names fonts refer to, which must live as long as the app
+static NAMES: RwLock<Vec<&'static str>> =
+    RwLock::new(vec![]);
+
+/// List the installed font families with `fc-list`, none
+/// being listed when fontconfig is missing
+pub fn load_system_fonts() {
+    let Ok(output) = Command::new("fc-list")
+        .args([":", "family"])
+        .output()
+    else {
+        return;
+    };
+
+    // A font may have several names, e.g.
+    // `DejaVu Sans,DejaVu Sans Condensed`
+    let mut families =
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split(',').next())
+            .map(|family| family.trim().to_string())
+            .filter(|family| !family.is_empty())
+            .collect::<Vec<_>>();
+    families.sort();
+    families.dedup();
+
+    if let Ok(mut loaded) = FAMILIES.write() {
+        *loaded = families;
+    }
+}
+
+/// Generic families followed by the installed ones
+pub fn families() -> Vec<String> {
+    GENERIC_FAMILIES
+        .iter()
+        .map(|family| family.to_string())
+        .chain(
+            FAMILIES
+                .read()
+                .map(|families| families.clone())
+                .unwrap_or_default(),
+        )
+        .collect()
+}
+
+pub fn font(family: &str) -> Font {
+    let family = match family {
+        "Sans-serif" => Family::SansSerif,
+        "Serif" => Family::Serif,
+        "Monospace" => Family::Monospace,
+        name => Family::Name(static_name(name)),
+    };
+
+    Font { family, ..Font::DEFAULT }
+}
+
+/// Leak each name once, as the fonts of the view are built
+/// again on every redraw
+fn static_name(name: &str) -> &'static str {
+    if let Some(name) =
+        NAMES.read().ok().and_then(|names| {
+            names
+                .iter()
+                .find(|known| **known == name)
+                .copied()
+        })
+    {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(name.into());
+    if let Ok(mut names) = NAMES.write() {
+        names.push(name);
+    }
+    name
+}
+
+/// Font of the interface, the bundled Fira Sans when unset
+pub fn ui_font(settings: &ApplicationSettings) -> Font {
+    settings.ui_font.as_deref().map_or(Font::DEFAULT, font)
+}
+
+/// Font of the replies, the interface one when unset
+pub fn reply_font(settings: &ApplicationSettings) -> Font {
+    settings
+        .reply_font
+        .as_deref()
+        .map_or_else(|| ui_font(settings), font)
+}
+
+/// Font of the code blocks of replies, monospace when unset
+pub fn code_font(settings: &ApplicationSettings) -> Font {
+    settings
+        .code_font
+        .as_deref()
+        .map_or(Font::MONOSPACE, font)
+}
+
+pub fn clamp_zoom(zoom: f64) -> f64 {
+    if zoom.is_finite() {
+        zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+    } else {
+        DEFAULT_ZOOM
+    }
+}
+
+/// Zoom to keep after a change, rounded so that repeated
+/// steps do not drift
+pub fn round_zoom(zoom: f64) -> f64 {
+    clamp_zoom((zoom * 100.0).round() / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_stays_within_bounds() {
+        assert_eq!(clamp_zoom(1.5), 1.5);
+        assert_eq!(clamp_zoom(0.1), MIN_ZOOM);
+        assert_eq!(clamp_zoom(10.0), MAX_ZOOM);
+        assert_eq!(clamp_zoom(f64::NAN), DEFAULT_ZOOM);
+        assert_eq!(clamp_zoom(f64::INFINITY), DEFAULT_ZOOM);
+    }
+
+    #[test]
+    fn repeated_steps_do_not_drift() {
+        let mut zoom = DEFAULT_ZOOM;
+        for _ in 0..5 {
+            zoom = round_zoom(zoom + ZOOM_STEP);
+        }
+        assert_eq!(zoom, 1.5);
+        for _ in 0..5 {
+            zoom = round_zoom(zoom - ZOOM_STEP);
+        }
+        assert_eq!(zoom, DEFAULT_ZOOM);
+
+        assert_eq!(
+            round_zoom(MAX_ZOOM + ZOOM_STEP),
+            MAX_ZOOM
+        );
+        assert_eq!(round_zoom(1.234), 1.23);
+    }
+}
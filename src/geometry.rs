@@ -0,0 +1,521 @@
+use std::{fmt, process::Command};
+
+use iced::{Point, Size};
+
+use crate::{config::ApplicationSettings, t};
+
+/// Size of the window until the user resizes it
+pub const DEFAULT_SIZE: Size = Size::new(800.0, 300.0);
+pub const MIN_SIZE: Size = Size::new(480.0, 240.0);
+/// Height of the window in expanded mode, for long answers
+pub const EXPANDED_HEIGHT: f32 = 720.0;
+/// Height of the home view taken by everything but the
+/// reply
+const REPLY_CHROME_HEIGHT: f32 = 160.0;
+const MIN_REPLY_HEIGHT: f32 = 80.0;
+/// Key the geometry is saved under when monitors cannot be
+/// listed, e.g. on Wayland
+const DEFAULT_MONITOR: &str = "default";
+
+/// Where the overlay shows up
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+)]
+pub enum WindowPlacement {
+    Center,
+    /// Centered near the top, like a launcher
+    TopCenter,
+    /// Centered on the monitor the mouse cursor is on
+    CursorMonitor,
+    #[default]
+    LastPosition,
+}
+
+impl WindowPlacement {
+    pub const ALL: [WindowPlacement; 4] = [
+        WindowPlacement::Center,
+        WindowPlacement::TopCenter,
+        WindowPlacement::CursorMonitor,
+        WindowPlacement::LastPosition,
+    ];
+}
+
+impl fmt::Display for WindowPlacement {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(&t!(match self {
+            WindowPlacement::Center => "placement-center",
+            WindowPlacement::TopCenter => {
+                "placement-top-center"
+            }
+            WindowPlacement::CursorMonitor => {
+                "placement-cursor-monitor"
+            }
+            WindowPlacement::LastPosition => {
+                "placement-last-position"
+            }
+        }))
+    }
+}
+
+/// Position and size of the window, in logical pixels
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+)]
+pub struct WindowGeometry {
+    /// Unknown until the window is first moved
+    pub position: Option<(i32, i32)>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self {
+            position: None,
+            width: DEFAULT_SIZE.width,
+            height: DEFAULT_SIZE.height,
+        }
+    }
+}
+
+impl WindowGeometry {
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Height left to the reply in the home view
+    pub fn reply_height(&self) -> f32 {
+        (self.height - REPLY_CHROME_HEIGHT)
+            .max(MIN_REPLY_HEIGHT)
+    }
+}
+
+/// Monitor as listed by `xrandr`, in physical pixels until
+/// [`Monitor::to_logical`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Physical size in millimeters, 0 when unknown
+    pub width_mm: i32,
+    pub height_mm: i32,
+    pub is_primary: bool,
+}
+
+impl Monitor {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        (self.x..self.x + self.width).contains(&x)
+            && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Scale factor windows on the monitor are drawn with,
+    /// found the way winit does on X11: from
+    /// `WINIT_X11_SCALE_FACTOR`, then `Xft.dpi`, then the
+    /// density of the monitor
+    fn scale_factor(&self, xft_dpi: Option<f64>) -> f64 {
+        if let Some(factor) =
+            std::env::var("WINIT_X11_SCALE_FACTOR")
+                .ok()
+                .and_then(|factor| {
+                    factor.parse::<f64>().ok()
+                })
+                .filter(|factor| *factor > 0.0)
+        {
+            return factor;
+        }
+        if let Some(dpi) = xft_dpi {
+            return dpi / 96.0;
+        }
+        if self.width_mm <= 0 || self.height_mm <= 0 {
+            return 1.0;
+        }
+
+        let pixels_per_mm = ((self.width * self.height)
+            as f64
+            / (self.width_mm * self.height_mm) as f64)
+            .sqrt();
+        // Rounded to twelfths, never below 1 like winit
+        ((pixels_per_mm * 12.0 * 25.4 / 96.0).round()
+            / 12.0)
+            .max(1.0)
+    }
+
+    /// Bounds in the logical pixels windows are moved in
+    fn to_logical(&self, scale_factor: f64) -> Self {
+        let logical = |pixels: i32| {
+            (pixels as f64 / scale_factor) as i32
+        };
+
+        Self {
+            x: logical(self.x),
+            y: logical(self.y),
+            width: logical(self.width),
+            height: logical(self.height),
+            ..self.clone()
+        }
+    }
+}
+
+/// Connected monitors in logical pixels, none when they
+/// cannot be listed
+pub fn monitors() -> Vec<Monitor> {
+    let xft_dpi = xft_dpi();
+
+    physical_monitors()
+        .into_iter()
+        .map(|monitor| {
+            monitor
+                .to_logical(monitor.scale_factor(xft_dpi))
+        })
+        .collect()
+}
+
+fn physical_monitors() -> Vec<Monitor> {
+    let Ok(output) = Command::new("xrandr")
+        .arg("--listactivemonitors")
+        .output()
+    else {
+        return vec![];
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_monitor)
+        .collect()
+}
+
+/// Density set in the X resources, which scales every
+/// monitor alike
+fn xft_dpi() -> Option<f64> {
+    let output =
+        Command::new("xrdb").arg("-query").output().ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("Xft.dpi:")?
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+        .filter(|dpi| *dpi > 0.0)
+}
+
+/// Parse a line like
+/// ` 0: +*eDP-1 1920/344x1080/194+0+0  eDP-1`
+fn parse_monitor(line: &str) -> Option<Monitor> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let [_, flags, geometry, name] = words[..] else {
+        return None;
+    };
+
+    let mut parts = geometry.split('+');
+    let (width, height) = parts.next()?.split_once('x')?;
+    let (width, width_mm) =
+        width.split_once('/').unwrap_or((width, "0"));
+    let (height, height_mm) =
+        height.split_once('/').unwrap_or((height, "0"));
+
+    Some(Monitor {
+        name: name.to_string(),
+        x: parts.next()?.parse().ok()?,
+        y: parts.next()?.parse().ok()?,
+        width: width.parse().ok()?,
+        height: height.parse().ok()?,
+        width_mm: width_mm.parse().unwrap_or(0),
+        height_mm: height_mm.parse().unwrap_or(0),
+        is_primary: flags.contains('*'),
+    })
+}
+
+/// Monitor the mouse cursor is on, in logical pixels
+fn cursor_monitor() -> Option<Monitor> {
+    let cursor = cursor_position()?;
+    let monitor = physical_monitors()
+        .into_iter()
+        .find(|monitor| monitor.contains(cursor))?;
+
+    Some(
+        monitor.to_logical(monitor.scale_factor(xft_dpi())),
+    )
+}
+
+/// Position of the mouse cursor on the screen in physical
+/// pixels, as told by `xdotool`
+fn cursor_position() -> Option<(i32, i32)> {
+    let output = Command::new("xdotool")
+        .args(["getmouselocation", "--shell"])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let coordinate = |name: &str| {
+        output.lines().find_map(|line| {
+            line.strip_prefix(name)?
+                .strip_prefix('=')?
+                .parse()
+                .ok()
+        })
+    };
+
+    Some((coordinate("X")?, coordinate("Y")?))
+}
+
+/// Where to put the window for the placement, `None` when it
+/// cannot be told, e.g. on Wayland where the compositor
+/// places windows itself. Runs `xrandr` and `xdotool`, so
+/// it blocks
+pub fn placement_position(
+    placement: WindowPlacement,
+    geometry: &WindowGeometry,
+) -> Option<Point> {
+    if placement == WindowPlacement::LastPosition {
+        return geometry
+            .position
+            .map(|(x, y)| Point::new(x as f32, y as f32));
+    }
+
+    let monitors = monitors();
+    let primary = || {
+        monitors
+            .iter()
+            .find(|monitor| monitor.is_primary)
+            .or(monitors.first())
+    };
+    let monitor = match placement {
+        WindowPlacement::CursorMonitor => {
+            cursor_monitor().or_else(|| primary().cloned())
+        }
+        _ => {
+            let current = monitor_of(&monitors, geometry);
+            monitors
+                .iter()
+                .find(|monitor| monitor.name == current)
+                .or_else(primary)
+                .cloned()
+        }
+    }?;
+
+    let x = monitor.x as f32
+        + (monitor.width as f32 - geometry.width) / 2.0;
+    let y = if placement == WindowPlacement::TopCenter {
+        monitor.y as f32 + monitor.height as f32 / 5.0
+    } else {
+        monitor.y as f32
+            + (monitor.height as f32 - geometry.height)
+                / 2.0
+    };
+
+    Some(Point::new(
+        x.max(monitor.x as f32),
+        y.max(monitor.y as f32),
+    ))
+}
+
+/// Name of the monitor the middle of the window is on
+pub fn monitor_of(
+    monitors: &[Monitor],
+    geometry: &WindowGeometry,
+) -> String {
+    geometry
+        .position
+        .and_then(|(x, y)| {
+            let center = (
+                x + geometry.width as i32 / 2,
+                y + geometry.height as i32 / 2,
+            );
+            monitors
+                .iter()
+                .find(|monitor| monitor.contains(center))
+        })
+        .map(|monitor| monitor.name.clone())
+        .unwrap_or(DEFAULT_MONITOR.to_string())
+}
+
+/// Geometry to open with, the one of the monitor used last
+/// when it is still connected, else of another connected one
+/// or of an unknown monitor
+pub fn saved_geometry(
+    settings: &ApplicationSettings,
+) -> Option<WindowGeometry> {
+    let monitors = monitors();
+    let is_connected = |name: &String| {
+        name == DEFAULT_MONITOR
+            || monitors.iter().any(|m| m.name == *name)
+    };
+
+    settings
+        .last_monitor
+        .iter()
+        .chain(settings.window_geometry.keys())
+        .filter(|name| is_connected(name))
+        .find_map(|name| settings.window_geometry.get(name))
+        .copied()
+}
+
+/// Monitor the window is on, found with `xrandr` so better
+/// kept off the UI thread
+pub fn current_monitor(
+    geometry: &WindowGeometry,
+) -> String {
+    monitor_of(&monitors(), geometry)
+}
+
+/// Remember the geometry for the monitor the window is on
+pub fn save_geometry(
+    settings: &mut ApplicationSettings,
+    monitor: String,
+    geometry: WindowGeometry,
+) {
+    settings
+        .window_geometry
+        .insert(monitor.clone(), geometry);
+    settings.last_monitor = Some(monitor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_xrandr_monitors() {
+        assert_eq!(
+            parse_monitor(
+                " 0: +*eDP-1 3840/344x2160/194+0+0  eDP-1"
+            ),
+            Some(Monitor {
+                name: "eDP-1".to_string(),
+                x: 0,
+                y: 0,
+                width: 3840,
+                height: 2160,
+                width_mm: 344,
+                height_mm: 194,
+                is_primary: true,
+            })
+        );
+
+        let external = parse_monitor(
+            " 1: +HDMI-1 1920/527x1080/296+3840+0  HDMI-1",
+        )
+        .unwrap();
+        assert_eq!((external.x, external.y), (3840, 0));
+        assert!(!external.is_primary);
+    }
+
+    #[test]
+    fn skips_other_xrandr_lines() {
+        for line in [
+            "Monitors: 2",
+            "",
+            " 0: +*eDP-1 wide+0+0  eDP-1",
+            " 0: +*eDP-1 1920/344x1080/194+0  eDP-1",
+        ] {
+            assert_eq!(parse_monitor(line), None, "{line}");
+        }
+    }
+
+    #[test]
+    fn logical_bounds_are_divided_by_the_scale() {
+        let monitor = parse_monitor(
+            " 1: +HDMI-1 3840/600x2160/340+3840+0  HDMI-1",
+        )
+        .unwrap()
+        .to_logical(2.0);
+
+        assert_eq!((monitor.x, monitor.y), (1920, 0));
+        assert_eq!(
+            (monitor.width, monitor.height),
+            (1920, 1080)
+        );
+    }
+
+    #[test]
+    fn scale_follows_dpi_then_density() {
+        if std::env::var_os("WINIT_X11_SCALE_FACTOR")
+            .is_some()
+        {
+            return;
+        }
+        let monitor = |line| parse_monitor(line).unwrap();
+        let laptop = monitor(
+            " 0: +*eDP-1 2560/338x1440/190+0+0  eDP-1",
+        );
+        let unknown = monitor(
+            " 0: +*VIRTUAL1 1920/0x1080/0+0+0  VIRTUAL1",
+        );
+
+        assert_eq!(laptop.scale_factor(Some(144.0)), 1.5);
+        assert_eq!(laptop.scale_factor(None), 2.0);
+        assert_eq!(unknown.scale_factor(None), 1.0);
+    }
+
+    #[test]
+    fn monitor_of_the_window_center() {
+        let monitors = [
+            Monitor {
+                name: "left".to_string(),
+                is_primary: true,
+                ..monitor_fixture()
+            },
+            Monitor {
+                name: "right".to_string(),
+                x: 1920,
+                ..monitor_fixture()
+            },
+        ];
+        let geometry = |position| WindowGeometry {
+            position,
+            ..WindowGeometry::default()
+        };
+
+        assert_eq!(
+            monitor_of(
+                &monitors,
+                &geometry(Some((100, 100)))
+            ),
+            "left"
+        );
+        assert_eq!(
+            monitor_of(
+                &monitors,
+                &geometry(Some((1700, 0)))
+            ),
+            "right"
+        );
+        assert_eq!(
+            monitor_of(&monitors, &geometry(None)),
+            DEFAULT_MONITOR
+        );
+    }
+
+    fn monitor_fixture() -> Monitor {
+        Monitor {
+            name: String::new(),
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+            width_mm: 0,
+            height_mm: 0,
+            is_primary: false,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    api::{self, RunningModel},
+    t,
+};
+
+/// Delay between checks while the server answers
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Delays between checks while the server is down, doubling
+/// from the first to the last
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// State of the Ollama server at the last check
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    pub version: Option<String>,
+    pub latency: Duration,
+    pub loaded_models: Vec<RunningModel>,
+    pub error: Option<String>,
+}
+
+impl HealthReport {
+    pub fn is_live(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Details shown when hovering the status indicator
+    pub fn describe(&self) -> String {
+        if let Some(err) = &self.error {
+            return t!(
+                "health-unreachable",
+                error = err.as_str()
+            );
+        }
+
+        let models = if self.loaded_models.is_empty() {
+            t!("health-no-model-loaded")
+        } else {
+            self.loaded_models
+                .iter()
+                .map(|model| {
+                    t!(
+                        "health-loaded-model",
+                        model = model.name.as_str(),
+                        megabytes =
+                            model.size_vram / 1_000_000
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let version = t!(
+            "health-version",
+            version =
+                self.version.as_deref().unwrap_or("?"),
+            latency = self.latency.as_millis().to_string()
+        );
+        format!("{version}\n{models}")
+    }
+}
+
+pub async fn check_health() -> HealthReport {
+    let started = Instant::now();
+
+    match api::version().await {
+        Ok(version) => HealthReport {
+            version: Some(version),
+            latency: started.elapsed(),
+            loaded_models: api::running_models()
+                .await
+                .unwrap_or_default(),
+            error: None,
+        },
+        Err(err) => HealthReport {
+            version: None,
+            latency: started.elapsed(),
+            loaded_models: vec![],
+            error: Some(err),
+        },
+    }
+}
+
+/// Delay until the next check, backing off while the server
+/// keeps failing
+pub fn next_check_in(failures: u32) -> Duration {
+    match failures {
+        0 => CHECK_INTERVAL,
+        _ => api::backoff_delay(
+            failures,
+            FIRST_RETRY_DELAY,
+            MAX_RETRY_DELAY,
+        ),
+    }
+}
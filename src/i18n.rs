@@ -0,0 +1,263 @@
+use std::{fmt, sync::RwLock};
+
+use fluent_bundle::{
+    concurrent::FluentBundle, FluentArgs, FluentResource,
+};
+use lazy_static::lazy_static;
+use unic_langid::LanguageIdentifier;
+
+use crate::debug;
+
+/// Language the interface is shown in
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+)]
+pub enum Language {
+    English,
+    French,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] =
+        [Language::English, Language::French];
+
+    fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::French => "fr",
+        }
+    }
+
+    fn catalog(&self) -> &'static str {
+        match self {
+            Language::English => {
+                include_str!("../locales/en.ftl")
+            }
+            Language::French => {
+                include_str!("../locales/fr.ftl")
+            }
+        }
+    }
+
+    /// Name in English, as told to the model
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::French => "French",
+        }
+    }
+
+    /// Language of the environment, from `LC_ALL`,
+    /// `LC_MESSAGES` or `LANG`, English when unsupported
+    pub fn detect() -> Self {
+        Self::from_locales(
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .into_iter()
+                .filter_map(|name| {
+                    std::env::var(name).ok()
+                }),
+        )
+    }
+
+    /// Language of the first locale set, in order of
+    /// precedence
+    fn from_locales(
+        locales: impl IntoIterator<Item = String>,
+    ) -> Self {
+        locales
+            .into_iter()
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| {
+                // e.g. `fr_FR.UTF-8`
+                let code = locale
+                    .split(['_', '.', '@', '-'])
+                    .next()?
+                    .to_lowercase();
+                Language::ALL.into_iter().find(|language| {
+                    language.code() == code
+                })
+            })
+            .unwrap_or(Language::English)
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        f.write_str(match self {
+            Language::English => "English",
+            Language::French => "Français",
+        })
+    }
+}
+
+type Bundle = FluentBundle<FluentResource>;
+
+lazy_static! {
+    static ref BUNDLES: Vec<(Language, Bundle)> =
+        Language::ALL
+            .into_iter()
+            .map(|language| (language, bundle(language)))
+            .collect();
+}
+
+/// Language picked in the settings, or detected
+static LANGUAGE: RwLock<Option<Language>> =
+    RwLock::new(None);
+
+fn bundle(language: Language) -> Bundle {
+    let id = language
+        .code()
+        .parse::<LanguageIdentifier>()
+        .unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // Isolation marks show up as boxes in the interface
+    bundle.set_use_isolating(false);
+
+    let resource = FluentResource::try_new(
+        language.catalog().to_string(),
+    )
+    .unwrap_or_else(|(resource, errors)| {
+        debug!(errors);
+        resource
+    });
+    if let Err(errors) = bundle.add_resource(resource) {
+        debug!(errors);
+    }
+
+    bundle
+}
+
+/// Use that language, or the one of the environment when
+/// `None`
+pub fn set_language(language: Option<Language>) {
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current =
+            Some(language.unwrap_or_else(Language::detect));
+    }
+}
+
+pub fn current_language() -> Language {
+    LANGUAGE
+        .read()
+        .ok()
+        .and_then(|language| *language)
+        .unwrap_or_else(Language::detect)
+}
+
+/// Message of the catalog of the current language, the
+/// English one being used when it is missing there
+pub fn translate(
+    id: &str,
+    args: Option<&FluentArgs>,
+) -> String {
+    let language = current_language();
+
+    [language, Language::English]
+        .iter()
+        .find_map(|language| {
+            let (_, bundle) = BUNDLES
+                .iter()
+                .find(|(l, _)| l == language)?;
+            let pattern =
+                bundle.get_message(id)?.value()?;
+            let mut errors = vec![];
+            let text = bundle.format_pattern(
+                pattern,
+                args,
+                &mut errors,
+            );
+            if !errors.is_empty() {
+                debug!(errors);
+            }
+            Some(text.to_string())
+        })
+        .unwrap_or_else(|| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(locales: &[&str]) -> Language {
+        Language::from_locales(
+            locales.iter().map(|locale| locale.to_string()),
+        )
+    }
+
+    #[test]
+    fn detects_the_language_of_the_locale() {
+        assert_eq!(
+            detect(&["fr_FR.UTF-8"]),
+            Language::French
+        );
+        assert_eq!(detect(&["fr"]), Language::French);
+        assert_eq!(detect(&["FR-ca"]), Language::French);
+        assert_eq!(
+            detect(&["en_US.UTF-8"]),
+            Language::English
+        );
+    }
+
+    #[test]
+    fn first_set_locale_wins() {
+        assert_eq!(
+            detect(&["", "fr_BE@euro", "en_GB"]),
+            Language::French
+        );
+        assert_eq!(
+            detect(&["en_GB", "fr_FR"]),
+            Language::English
+        );
+    }
+
+    #[test]
+    fn catalogs_parse_and_define_the_same_messages() {
+        let ids = |language: Language| {
+            if let Err((_, errors)) =
+                FluentResource::try_new(
+                    language.catalog().to_string(),
+                )
+            {
+                panic!("{language:?}: {errors:?}");
+            }
+            let mut ids = language
+                .catalog()
+                .lines()
+                .filter(|line| {
+                    line.starts_with(|c: char| {
+                        c.is_ascii_alphabetic()
+                    })
+                })
+                .filter_map(|line| {
+                    Some(line.split_once(" = ")?.0)
+                })
+                .collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(
+            ids(Language::English),
+            ids(Language::French)
+        );
+    }
+
+    #[test]
+    fn unsupported_locales_fall_back_to_english() {
+        assert_eq!(
+            detect(&["de_DE.UTF-8"]),
+            Language::English
+        );
+        assert_eq!(detect(&["C"]), Language::English);
+        assert_eq!(detect(&[]), Language::English);
+    }
+}
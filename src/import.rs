@@ -0,0 +1,368 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{
+    chat::{Conversation, Role},
+    conversations::ConversationStore,
+    export::{Transcript, TRANSCRIPT_VERSION},
+    t,
+};
+
+/// Longest title derived from the first prompt of an
+/// imported conversation
+const IMPORTED_TITLE_LENGTH: usize = 40;
+
+/// Outcome of an import, malformed entries being skipped.
+/// The imported conversation is the selected one
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    pub title: String,
+    pub imported: usize,
+    pub skipped: Vec<String>,
+}
+
+impl std::fmt::Display for ImportReport {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(&t!(
+            "import-done",
+            messages = self.imported,
+            title = self.title.as_str()
+        ))?;
+        if !self.skipped.is_empty() {
+            write!(
+                f,
+                "\n{}\n{}",
+                t!(
+                    "import-skipped",
+                    count = self.skipped.len()
+                ),
+                self.skipped.join("\n")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn import_file(
+    path: &Path,
+    store: &mut ConversationStore,
+) -> Result<ImportReport, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| {
+            t!(
+                "error-cannot-read",
+                path = path.display().to_string(),
+                error = err.to_string()
+            )
+        })?;
+
+    import_transcript(&contents, store)
+}
+
+/// Import either a transcript exported by the overlay, or an
+/// OpenAI style `messages` array, as a new conversation
+pub fn import_transcript(
+    contents: &str,
+    store: &mut ConversationStore,
+) -> Result<ImportReport, String> {
+    let value = serde_json::from_str::<Value>(contents)
+        .map_err(|err| {
+            t!(
+                "import-invalid-json",
+                error = err.to_string()
+            )
+        })?;
+
+    match &value {
+        Value::Object(object)
+            if object.contains_key("conversation") =>
+        {
+            import_overlay_transcript(value, store)
+        }
+        Value::Object(object) => {
+            match object.get("messages") {
+                Some(Value::Array(messages)) => {
+                    import_messages(messages, store)
+                }
+                _ => Err(t!("import-unrecognized")),
+            }
+        }
+        Value::Array(messages) => {
+            import_messages(messages, store)
+        }
+        _ => Err(t!("import-unrecognized")),
+    }
+}
+
+fn import_overlay_transcript(
+    value: Value,
+    store: &mut ConversationStore,
+) -> Result<ImportReport, String> {
+    let transcript =
+        serde_json::from_value::<Transcript>(value)
+            .map_err(|err| {
+                t!(
+                    "import-invalid-transcript",
+                    error = err.to_string()
+                )
+            })?;
+
+    if transcript.version > TRANSCRIPT_VERSION {
+        return Err(t!(
+            "import-newer-version",
+            version = transcript.version,
+            supported = TRANSCRIPT_VERSION
+        ));
+    }
+
+    let saved = transcript.conversation;
+    saved.conversation.validate()?;
+
+    let imported = saved.conversation.active_path().len();
+    store.insert(
+        saved.title.clone(),
+        saved.titled,
+        saved.conversation,
+    );
+
+    Ok(ImportReport {
+        title: saved.title,
+        imported,
+        skipped: vec![],
+    })
+}
+
+fn import_messages(
+    messages: &[Value],
+    store: &mut ConversationStore,
+) -> Result<ImportReport, String> {
+    let mut conversation = Conversation::default();
+    let mut skipped = vec![];
+    let mut imported = 0;
+
+    for (index, entry) in messages.iter().enumerate() {
+        match parse_message(entry) {
+            Ok((role, content)) => {
+                conversation.push(role, content);
+                imported += 1;
+            }
+            Err(err) => skipped.push(t!(
+                "import-entry-skipped",
+                entry = index + 1,
+                error = err
+            )),
+        }
+    }
+
+    if imported == 0 {
+        return Err(format!(
+            "{}{}",
+            t!("import-no-valid-message"),
+            skipped
+                .iter()
+                .map(|err| format!("\n{err}"))
+                .collect::<String>()
+        ));
+    }
+
+    let title = conversation
+        .active_path()
+        .into_iter()
+        .map(|id| conversation.message(id))
+        .find(|message| message.role == Role::User)
+        .map(|message| {
+            message
+                .content
+                .chars()
+                .take(IMPORTED_TITLE_LENGTH)
+                .collect::<String>()
+                .trim()
+                .to_string()
+        })
+        .unwrap_or_else(|| t!("import-default-title"));
+
+    store.insert(title.clone(), true, conversation);
+
+    Ok(ImportReport { title, imported, skipped })
+}
+
+fn parse_message(
+    entry: &Value,
+) -> Result<(Role, String), String> {
+    let entry = entry
+        .as_object()
+        .ok_or_else(|| t!("import-not-an-object"))?;
+
+    let role = match entry
+        .get("role")
+        .and_then(Value::as_str)
+    {
+        Some("system") | Some("developer") => Role::System,
+        Some("user") => Role::User,
+        Some("assistant") => Role::Assistant,
+        Some(other) => {
+            return Err(t!(
+                "import-unsupported-role",
+                role = other
+            ))
+        }
+        None => return Err(t!("import-missing-role")),
+    };
+
+    let content = match entry.get("content") {
+        Some(Value::String(content)) => content.clone(),
+        // Content made of parts, only text parts are kept
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| {
+                part.get("text").and_then(Value::as_str)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(_) => {
+            return Err(t!("import-content-not-text"))
+        }
+        None => return Err(t!("import-missing-content")),
+    };
+
+    if content.trim().is_empty() {
+        return Err(t!("import-empty-content"));
+    }
+
+    Ok((role, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_openai_messages() {
+        let mut store = ConversationStore::default();
+        let report = import_transcript(
+            r#"{"messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hello there"},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Hi"}
+                ]}
+            ]}"#,
+            &mut store,
+        )
+        .unwrap();
+
+        assert_eq!(report.title, "Hello there");
+        assert_eq!(report.imported, 3);
+        assert!(report.skipped.is_empty());
+        assert_eq!(store.selected().title, "Hello there");
+        assert_eq!(store.current().active_path().len(), 3);
+    }
+
+    #[test]
+    fn skips_malformed_entries() {
+        let mut store = ConversationStore::default();
+        let report = import_transcript(
+            r#"[
+                {"role": "user", "content": "Hello"},
+                {"role": "tool", "content": "42"},
+                {"role": "assistant"},
+                {"role": "assistant", "content": "  "},
+                "not an object"
+            ]"#,
+            &mut store,
+        )
+        .unwrap();
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped.len(), 4);
+    }
+
+    #[test]
+    fn rejects_files_without_messages() {
+        let mut store = ConversationStore::default();
+
+        for contents in [
+            "not json",
+            "42",
+            r#"{"prompt": "Hello"}"#,
+            r#"[{"role": "user"}]"#,
+        ] {
+            assert!(
+                import_transcript(contents, &mut store)
+                    .is_err(),
+                "{contents} was imported"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_newer_and_inconsistent_transcripts() {
+        let mut store = ConversationStore::default();
+        let transcript = |version: u32, tree: &str| {
+            format!(
+                r#"{{
+                    "version": {version},
+                    "exported_at": 0,
+                    "model": "llama3",
+                    "conversation": {{
+                        "id": 1,
+                        "title": "Test",
+                        "titled": true,
+                        "pinned": false,
+                        "created_at": 0,
+                        "updated_at": 0,
+                        "conversation": {{
+                            "messages": {tree}
+                        }}
+                    }}
+                }}"#
+            )
+        };
+        let root = r#"[{"role": "system", "content": "",
+            "parent": null, "children": [],
+            "selected_child": 0}]"#;
+        let orphan = r#"[{"role": "system", "content": "",
+            "parent": null, "children": [1],
+            "selected_child": 0},
+            {"role": "user", "content": "Hi",
+            "parent": 0, "children": [],
+            "selected_child": 0},
+            {"role": "user", "content": "Lost",
+            "parent": 0, "children": [],
+            "selected_child": 0}]"#;
+        let cycle = r#"[{"role": "system", "content": "",
+            "parent": null, "children": [],
+            "selected_child": 0},
+            {"role": "user", "content": "Hi",
+            "parent": 2, "children": [2],
+            "selected_child": 0},
+            {"role": "assistant", "content": "Hello",
+            "parent": 1, "children": [1],
+            "selected_child": 0}]"#;
+
+        assert!(import_transcript(
+            &transcript(TRANSCRIPT_VERSION, root),
+            &mut store
+        )
+        .is_ok());
+        assert!(import_transcript(
+            &transcript(TRANSCRIPT_VERSION + 1, root),
+            &mut store
+        )
+        .is_err());
+        assert!(import_transcript(
+            &transcript(TRANSCRIPT_VERSION, orphan),
+            &mut store
+        )
+        .is_err());
+        assert!(import_transcript(
+            &transcript(TRANSCRIPT_VERSION, cycle),
+            &mut store
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    os::unix::{fs::MetadataExt, net::UnixStream},
+    path::{Path, PathBuf},
+};
+
+use iced::{
+    futures::{never::Never, SinkExt},
+    subscription, Subscription,
+};
+use tokio::net::UnixListener;
+
+use crate::debug;
+
+const SOCKET_NAME: &str = "ai-overlay.sock";
+
+/// Socket the running overlay listens on to be shown again.
+/// The temporary folder being shared, the socket is named
+/// after the user there
+fn socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(directory) => {
+            PathBuf::from(directory).join(SOCKET_NAME)
+        }
+        None => std::env::temp_dir()
+            .join(format!("ai-overlay-{}.sock", user_id())),
+    }
+}
+
+/// Id of the user running the overlay, as the owner of its
+/// process
+fn user_id() -> String {
+    std::fs::metadata("/proc/self")
+        .map(|metadata| metadata.uid().to_string())
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_default()
+}
+
+/// Ask an overlay already running to show itself, `true`
+/// when there was one
+pub fn activate_running_instance() -> bool {
+    UnixStream::connect(socket_path())
+        .and_then(|mut stream| stream.write_all(b"show"))
+        .is_ok()
+}
+
+/// Each time the overlay is started again while running
+pub fn activations() -> Subscription<()> {
+    subscription::channel(
+        "instance-activations",
+        10,
+        |mut output| async move {
+            match bind(&socket_path()) {
+                Ok(listener) => loop {
+                    if listener.accept().await.is_ok() {
+                        let _ = output.send(()).await;
+                    }
+                },
+                Err(err) => {
+                    debug!(err);
+                    std::future::pending::<Never>().await
+                }
+            }
+        },
+    )
+}
+
+/// Listen on the socket, replacing it only when it was left
+/// behind by an overlay that did not stop cleanly, so that
+/// the socket of a running one is never taken over
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    match UnixListener::bind(path) {
+        Err(err) if err.kind() == ErrorKind::AddrInUse => {
+            let is_stale = UnixStream::connect(path)
+                .is_err_and(|err| {
+                    err.kind()
+                        == ErrorKind::ConnectionRefused
+                });
+            if !is_stale {
+                return Err(err);
+            }
+
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)
+        }
+        result => result,
+    }
+}
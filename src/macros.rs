@@ -13,3 +13,19 @@ macro_rules! debug {
         }
     };
 }
+
+/// Message of the interface catalogs in the current
+/// language, e.g. `t!("model-loaded", model = name)`
+#[macro_export]
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::translate($id, None)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        {
+            let mut args = fluent_bundle::FluentArgs::new();
+            $(args.set(stringify!($name), $value);)+
+            $crate::i18n::translate($id, Some(&args))
+        }
+    };
+}
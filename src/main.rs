@@ -1,56 +1,270 @@
+use std::{collections::HashSet, time::Instant};
+
 use config::ApplicationSettings;
 use iced::keyboard::key::Named;
-use iced::widget::{column, combo_box, container, svg};
-use iced::window::Position;
+use iced::widget::{
+    column, container, row, scrollable, svg,
+};
+use iced::window::{Level, Position};
 use iced::{
     executor, keyboard, window, Application, Command,
-    Element, Length, Settings, Size, Subscription,
+    Element, Length, Point, Settings, Size, Subscription,
 };
 
-use ai::check_ai_health;
+use ai::{
+    AiOutcome, AiRequest, ModelAnswer, ModelStatus,
+    RetryState, ToolRequest,
+};
+use api::AiError;
+use chat::PendingReply;
+use compare::Comparison;
+use conversations::ConversationStore;
+use export::ExportFormat;
+use geometry::{WindowGeometry, WindowPlacement};
+use health::{check_health, HealthReport};
+use i18n::Language;
+use pull::{PullEvent, PullState};
+use rag::{DocumentsStatus, IndexSummary};
+use search::{SearchHit, SearchIndex};
 use styles::application::get_application_styles;
 use styles::container::get_container_style;
-use styles::get_theme_for_main_window;
+use styles::system::{self, ColorScheme};
+use styles::theme::{self, UserTheme};
+use styles::{get_theme_for_main_window, PADDING_SIZE};
 use ui::gui::{
-    main_page_content, settings_page_content, top_bar,
+    compare_page_content, conversations_sidebar,
+    main_page_content, missing_model_prompt, prompt_bar,
+    reply_content, search_page_content,
+    settings_page_content, top_bar, usage_page_content,
 };
 use ui::RouterView;
 use update::handle_update;
+use usage::UsageStore;
 
 mod ai;
+mod api;
+mod chat;
+mod cli;
+mod compare;
 mod config;
+mod conversations;
+mod export;
+mod fonts;
+mod geometry;
+mod health;
+mod i18n;
+mod import;
+mod instance;
 mod macros;
+mod pull;
+mod rag;
+mod search;
+mod structured;
 mod styles;
+mod tools;
 mod ui;
 mod update;
+mod usage;
 
 pub fn main() -> iced::Result {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match cli::parse_args(&args).and_then(|command| {
+        command.map(cli::run).transpose()
+    }) {
+        Ok(None) => {}
+        Ok(Some(output)) => {
+            println!("{output}");
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    if instance::activate_running_instance() {
+        return Ok(());
+    }
+
+    let config = config::load_settings();
+    i18n::set_language(config.language);
+    fonts::load_system_fonts();
+    let themes = theme::load_themes();
+    theme::select_theme(&themes, config.theme.as_deref());
+    theme::apply_appearance(&config);
+    let geometry = geometry::saved_geometry(&config)
+        .unwrap_or_default();
+
     let settings = Settings {
         window: window::Settings {
             decorations: false,
-            size: Size::new(800.0, 300.0),
-            position: Position::Centered,
-            resizable: false,
+            size: geometry.size(),
+            position: geometry::placement_position(
+                config.placement,
+                &geometry,
+            )
+            .map_or(Position::Centered, Position::Specific),
+            level: window_level(config.always_on_top),
+            min_size: Some(geometry::MIN_SIZE),
+            resizable: true,
+            // Closing may only hide the window
+            exit_on_close_request: false,
             transparent: true,
             ..Default::default()
         },
         antialiasing: true,
+        default_font: fonts::ui_font(&config),
+        default_text_size: theme::current_theme()
+            .font_size
+            .into(),
+        flags: Flags { config, themes, geometry },
         ..Default::default()
     };
 
     App::run(settings)
 }
 
+/// What was loaded before opening the window, so the
+/// overlay starts without reading it again
+#[derive(Default)]
+pub struct Flags {
+    config: ApplicationSettings,
+    themes: Vec<UserTheme>,
+    geometry: WindowGeometry,
+}
+
+fn window_level(always_on_top: bool) -> Level {
+    if always_on_top {
+        Level::AlwaysOnTop
+    } else {
+        Level::Normal
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum MainMessage {
     UpdateInput(String),
     SendToAI,
-    AIResponse(Result<String, String>),
+    AIResponse(Result<AiOutcome, AiError>),
+    RetryRequest(u64),
+    ApproveToolCalls,
+    DenyToolCalls,
+    ToolsRan(ToolRequest),
+    ToggleJsonNode(String),
+    RegenerateReply,
+    EditLastPrompt,
+    CancelEdit,
+    PreviousBranch(usize),
+    NextBranch(usize),
+    ToggleSidebar,
+    NewConversation,
+    SelectConversation(u64),
+    StartRenameConversation(u64),
+    UpdateConversationTitle(String),
+    ConfirmRenameConversation,
+    TogglePinConversation(u64),
+    DeleteConversation(u64),
+    ConversationTitleGenerated(u64, Result<String, String>),
+    ExportConversation(u64, ExportFormat),
+    UpdateImportPath(String),
+    ImportConversation,
+    UpdateSearchQuery(String),
+    OpenSearchResult(u64, usize),
+    UpdateComparePrompt(String),
+    ToggleCompareModel(String, bool),
+    RunComparison,
+    ComparisonAnswer(
+        u64,
+        usize,
+        Result<ModelAnswer, String>,
+    ),
+    PromoteAnswer(usize),
     ChangeView(RouterView),
-    AiHealthCheck(bool),
+    AiHealthCheck(HealthReport),
     RunAiHealthCheck,
     UpdateConfigModel(String),
+    UpdateConfigKeepAlive(String),
+    /// Load the model ahead of the next prompt
+    WarmUpModel,
+    ModelWarmedUp(Result<(), String>),
+    UnloadModel,
+    ModelUnloaded(Result<(), String>),
+    UpdateConfigContextLength(String),
+    UpdateConfigDocumentsDir(String),
+    UpdateConfigEmbeddingModel(String),
+    ToggleTools(bool),
+    UpdateConfigWorkspaceDir(String),
+    UpdateConfigAllowedCommands(String),
+    UpdateConfigRequestTimeout(String),
+    UpdateConfigMaxRetries(String),
+    ToggleJsonMode(bool),
+    UpdateConfigJsonSchema(String),
+    IndexDocuments,
+    DocumentsIndexed(Result<IndexSummary, String>),
+    ModelContextLength(Option<u32>),
+    ConversationSummarized(
+        u64,
+        usize,
+        Result<String, String>,
+    ),
     UpdateAvailableModels(Vec<String>),
+    /// Installed models at startup, the configured one
+    /// being looked up among them
+    ModelsChecked(Result<Vec<String>, String>),
+    PullModel(String),
+    PullProgress(PullEvent),
+    /// The desktop switched between light and dark
+    ColorSchemeChanged(ColorScheme),
+    /// Theme to draw with, `None` to follow the system
+    SelectTheme(Option<String>),
+    ReloadThemes,
+    UpdateConfigOpacity(f32),
+    UpdateConfigBorderColor(String),
+    UpdateConfigCornerRadius(f32),
+    /// Go back to the appearance of the theme
+    ResetAppearance,
+    ToggleHighContrast(bool),
+    /// Move the window along with the mouse
+    DragWindow,
+    WindowResized(Size),
+    WindowMoved(i32, i32),
+    /// Save the geometry if it did not change again since
+    /// that revision
+    SaveWindowGeometry(u64),
+    /// Monitor the geometry of that revision is on, `None`
+    /// when it could not be found
+    WindowMonitorFound(u64, WindowGeometry, Option<String>),
+    /// Save the settings if they did not change again since
+    /// that revision
+    SaveSettings(u64),
+    ToggleExpanded,
+    /// Hide or quit, depending on the settings
+    CloseWindow,
+    WindowUnfocused,
+    /// The overlay was started again while running
+    ShowWindow,
+    /// Show the window again, moved to where the placement
+    /// puts it when known
+    ShowWindowAt(Option<Point>),
+    MoveWindowTo(Option<Point>),
+    ToggleHideOnClose(bool),
+    ToggleHideOnFocusLoss(bool),
+    ToggleAlwaysOnTop(bool),
+    UpdateConfigPlacement(WindowPlacement),
+    /// Interface language, `None` for the system one
+    SelectLanguage(Option<Language>),
+    ToggleReplyInUiLanguage(bool),
+    /// Font families, `None` for the default ones
+    UpdateConfigUiFont(Option<String>),
+    UpdateConfigReplyFont(Option<String>),
+    UpdateConfigCodeFont(Option<String>),
+    UpdateConfigZoom(f64),
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    /// Move the keyboard focus with Tab and Shift+Tab
+    FocusNext,
+    FocusPrevious,
     GetAvailableModels,
     Exit,
 }
@@ -63,28 +277,106 @@ pub enum AppState {
 
 pub struct App {
     text: String,
-    ai_response: String,
+    conversations: ConversationStore,
+    pending_reply: Option<(u64, PendingReply)>,
+    /// Request being answered, sent again when it fails
+    last_request: Option<AiRequest>,
+    /// Incremented on each request sent, so a retry only
+    /// resends the request it was scheduled for
+    request_revision: u64,
+    retry: Option<RetryState>,
+    /// Tool calls of the pending reply awaiting approval
+    tool_request: Option<ToolRequest>,
+    /// JSON pointers of the folded nodes of a JSON reply
+    collapsed_json_nodes: HashSet<String>,
+    is_editing_prompt: bool,
+    show_sidebar: bool,
+    renaming_conversation: Option<(u64, String)>,
+    conversations_notice: Option<String>,
+    import_path: String,
+    search_query: String,
+    search_index: SearchIndex,
+    search_results: Vec<SearchHit>,
+    comparison: Comparison,
+    usage: UsageStore,
+    model_context_length: Option<u32>,
+    /// Context window as typed in the settings, applied
+    /// once it is a valid one
+    context_length_input: String,
+    summarizing: bool,
+    is_indexing_documents: bool,
+    documents_status: Option<DocumentsStatus>,
     loading: AppState,
     error: Option<String>,
     view: RouterView,
     config_settings: ApplicationSettings,
-    is_ai_api_live: bool,
+    /// Last health check, `None` until the first one ends
+    health: Option<HealthReport>,
+    /// Failed health checks in a row
+    health_failures: u32,
     settings_icon: svg::Handle,
     back_icon: svg::Handle,
-    // current_theme: styles::AppTheme,
-    available_models: combo_box::State<String>,
+    installed_models: Vec<String>,
+    /// Model that was asked for but is not installed
+    missing_model: Option<String>,
+    pull: Option<PullState>,
+    /// Outcome of the last model load or unload
+    model_status: Option<ModelStatus>,
+    last_warm_up: Option<Instant>,
+    /// Built-in and user themes
+    themes: Vec<UserTheme>,
+    window_geometry: WindowGeometry,
+    /// Incremented on each move or resize, so the geometry
+    /// is only saved once the window settles
+    geometry_revision: u64,
+    /// Incremented on each change typed or dragged in the
+    /// settings, so they are only saved once it pauses
+    settings_revision: u64,
+    /// Size to go back to when leaving the expanded mode
+    size_before_expanding: Option<Size>,
+    /// Hidden rather than quit, see
+    /// [`ApplicationSettings::hide_on_close`]
+    is_hidden: bool,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(flags: Flags) -> Self {
+        let config_settings = flags.config;
+
         Self {
             text: "".to_string(),
-            ai_response: "".to_string(),
+            conversations:
+                conversations::load_conversations(),
+            pending_reply: None,
+            last_request: None,
+            request_revision: 0,
+            retry: None,
+            tool_request: None,
+            collapsed_json_nodes: HashSet::new(),
+            is_editing_prompt: false,
+            show_sidebar: false,
+            renaming_conversation: None,
+            conversations_notice: None,
+            import_path: "".to_string(),
+            search_query: "".to_string(),
+            search_index: SearchIndex::default(),
+            search_results: vec![],
+            comparison: Comparison::default(),
+            usage: usage::load_usage(),
+            model_context_length: None,
+            context_length_input: config_settings
+                .num_ctx
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            summarizing: false,
+            is_indexing_documents: false,
+            documents_status: None,
             loading: AppState::Done,
             error: None,
             view: RouterView::Home,
-            config_settings: config::load_settings(),
-            is_ai_api_live: false,
+            config_settings,
+            health: None,
+            health_failures: 0,
             settings_icon: svg::Handle::from_memory(
                 include_bytes!("../assets/settings.svg")
                     .to_vec(),
@@ -93,42 +385,72 @@ impl App {
                 include_bytes!("../assets/back.svg")
                     .to_vec(),
             ),
-            // current_theme: styles::get_app_theme(),
-            available_models: combo_box::State::new(vec![]),
+            installed_models: vec![],
+            missing_model: None,
+            pull: None,
+            model_status: None,
+            last_warm_up: None,
+            themes: flags.themes,
+            window_geometry: flags.geometry,
+            geometry_revision: 0,
+            settings_revision: 0,
+            size_before_expanding: None,
+            is_hidden: false,
         }
     }
 }
 
+impl App {
+    /// Context window the conversation has to fit in
+    fn context_length(&self) -> u32 {
+        self.config_settings
+            .num_ctx
+            .filter(|&n| n >= ai::MIN_NUM_CTX)
+            .or(self.model_context_length)
+            .unwrap_or(ai::DEFAULT_NUM_CTX)
+    }
+}
+
 impl Application for App {
     type Executor = executor::Default;
     type Message = MainMessage;
     type Theme = iced::Theme;
-    type Flags = ();
+    type Flags = Flags;
+
+    fn new(flags: Flags) -> (Self, Command<Self::Message>) {
+        let mut app = App::new(flags);
+        let settings = app.config_settings.clone();
+        let model = settings.ai_model.clone();
+        app.last_warm_up = Some(Instant::now());
+        app.model_status = Some(ModelStatus::Loading);
 
-    fn new(
-        _flags: Self::Flags,
-    ) -> (Self, Command<Self::Message>) {
         (
-            App::new(),
+            app,
             Command::batch(vec![
+                Command::perform(
+                    crate::ai::get_model_context_length(
+                        model,
+                    ),
+                    MainMessage::ModelContextLength,
+                ),
                 Command::perform(
                     crate::ai::get_ai_models_installed(),
-                    |v| {
-                        MainMessage::UpdateAvailableModels(
-                            v.unwrap_or_default(),
-                        )
-                    },
+                    MainMessage::ModelsChecked,
                 ),
                 Command::perform(
-                    check_ai_health(),
+                    check_health(),
                     MainMessage::AiHealthCheck,
                 ),
+                Command::perform(
+                    crate::ai::warm_up(settings),
+                    MainMessage::ModelWarmedUp,
+                ),
             ]),
         )
     }
 
     fn title(&self) -> String {
-        String::from("AI Overlay")
+        t!("app-title")
     }
 
     fn update(
@@ -138,14 +460,17 @@ impl Application for App {
         handle_update(self, message)
     }
 
-    fn view(&self) -> Element<MainMessage> {
+    fn view(&self) -> Element<'_, MainMessage> {
         let (header_icon, on_icon_click) = match &self.view
         {
             RouterView::Home => (
                 self.settings_icon.clone(),
                 RouterView::Settings,
             ),
-            RouterView::Settings => {
+            RouterView::Settings
+            | RouterView::Search
+            | RouterView::Compare
+            | RouterView::Usage => {
                 (self.back_icon.clone(), RouterView::Home)
             }
         };
@@ -153,26 +478,106 @@ impl Application for App {
         let header = top_bar(
             header_icon,
             on_icon_click,
-            self.is_ai_api_live,
+            &self.health,
         )
         .into();
 
         let content = match self.view {
-            RouterView::Home => main_page_content(
-                &self.loading,
-                &self.text,
-                &self.ai_response,
-                &self.error,
+            RouterView::Home => {
+                let conversation =
+                    self.conversations.current();
+                let prompt = prompt_bar(
+                    &self.text,
+                    self.is_editing_prompt,
+                    (!self.summarizing).then(|| {
+                        conversation.context_tokens(
+                            rag::excerpts_tokens(
+                                &self.config_settings,
+                            ),
+                        )
+                    }),
+                    self.context_length(),
+                );
+                let missing_model = self
+                    .missing_model
+                    .as_ref()
+                    .map(|model| {
+                        missing_model_prompt(
+                            model,
+                            &self.installed_models,
+                            &self.pull,
+                            &self.error,
+                        )
+                    });
+                let reply = conversation.last_reply().map(
+                    |reply| {
+                        reply_content(
+                            conversation,
+                            reply,
+                            &self.collapsed_json_nodes,
+                            &self.config_settings,
+                            self.window_geometry
+                                .reply_height(),
+                            self.size_before_expanding
+                                .is_some(),
+                        )
+                    },
+                );
+                let page = main_page_content(
+                    &self.loading,
+                    prompt,
+                    &self.retry,
+                    &self.tool_request,
+                    missing_model,
+                    &self.error,
+                    reply,
+                )
+                .into();
+
+                if self.show_sidebar {
+                    row![
+                        conversations_sidebar(
+                            &self.conversations,
+                            &self.renaming_conversation,
+                            &self.conversations_notice,
+                            &self.import_path,
+                        )
+                        .into(),
+                        page
+                    ]
+                    .spacing(PADDING_SIZE)
+                    .into()
+                } else {
+                    page
+                }
+            }
+            RouterView::Settings => scrollable(
+                settings_page_content(
+                    &self.installed_models,
+                    &self.config_settings,
+                    self.context_length(),
+                    &self.context_length_input,
+                    self.is_indexing_documents,
+                    &self.documents_status,
+                    &self.model_status,
+                    &self.themes,
+                )
+                .into(),
             )
             .into(),
-            RouterView::Settings => {
-                column![settings_page_content(
-                    &self.available_models,
-                    Some(&self.config_settings.ai_model)
-                )
-                .into()]
-                .into()
+            RouterView::Compare => compare_page_content(
+                &self.installed_models,
+                &self.comparison,
+            )
+            .into(),
+            RouterView::Usage => {
+                usage_page_content(&self.usage).into()
             }
+            RouterView::Search => search_page_content(
+                &self.search_query,
+                &self.search_results,
+            )
+            .into(),
         };
 
         container(column![header, content])
@@ -185,20 +590,130 @@ impl Application for App {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        keyboard::on_key_press(|key, _modifiers| match key
-            .as_ref()
-        {
-            keyboard::Key::Named(Named::Escape) => {
-                Some(MainMessage::Exit)
-            }
-            keyboard::Key::Named(Named::Backspace) => Some(
-                MainMessage::ChangeView(RouterView::Home),
-            ),
-            keyboard::Key::Named(Named::Enter) => {
-                Some(MainMessage::SendToAI)
+        let health_checks = iced::time::every(
+            health::next_check_in(self.health_failures),
+        )
+        .map(|_| MainMessage::RunAiHealthCheck);
+
+        let shortcuts =
+            keyboard::on_key_press(|key, modifiers| {
+                match key.as_ref() {
+                    keyboard::Key::Named(Named::Tab) => {
+                        Some(if modifiers.shift() {
+                            MainMessage::FocusPrevious
+                        } else {
+                            MainMessage::FocusNext
+                        })
+                    }
+                    // Focused widgets capture it to give the
+                    // focus up first
+                    keyboard::Key::Named(Named::Escape) => {
+                        Some(MainMessage::CloseWindow)
+                    }
+                    keyboard::Key::Named(
+                        Named::Backspace,
+                    ) => Some(MainMessage::ChangeView(
+                        RouterView::Home,
+                    )),
+                    keyboard::Key::Named(Named::Enter) => {
+                        Some(MainMessage::SendToAI)
+                    }
+                    _ => None,
+                }
+            });
+
+        // Listened to even when a text input has the
+        // focus, as it captures every key
+        let zoom_shortcuts =
+            iced::event::listen_with(|event, _| {
+                let iced::Event::Keyboard(
+                    keyboard::Event::KeyPressed {
+                        key,
+                        modifiers,
+                        ..
+                    },
+                ) = event
+                else {
+                    return None;
+                };
+                if !modifiers.command() {
+                    return None;
+                }
+
+                match key.as_ref() {
+                    // `=` is `+` without Shift on most
+                    // layouts
+                    keyboard::Key::Character("+" | "=") => {
+                        Some(MainMessage::ZoomIn)
+                    }
+                    keyboard::Key::Character("-") => {
+                        Some(MainMessage::ZoomOut)
+                    }
+                    keyboard::Key::Character("0") => {
+                        Some(MainMessage::ResetZoom)
+                    }
+                    _ => None,
+                }
+            });
+
+        let window_events = iced::event::listen_with(
+            |event, _| match event {
+                // Showing the overlay again loads the model
+                // back if it was unloaded meanwhile
+                iced::Event::Window(
+                    _,
+                    window::Event::Focused,
+                ) => Some(MainMessage::WarmUpModel),
+                iced::Event::Window(
+                    _,
+                    window::Event::Resized {
+                        width,
+                        height,
+                    },
+                ) => Some(MainMessage::WindowResized(
+                    Size::new(width as f32, height as f32),
+                )),
+                iced::Event::Window(
+                    _,
+                    window::Event::Moved { x, y },
+                ) => Some(MainMessage::WindowMoved(x, y)),
+                iced::Event::Window(
+                    _,
+                    window::Event::Unfocused,
+                ) => Some(MainMessage::WindowUnfocused),
+                iced::Event::Window(
+                    _,
+                    window::Event::CloseRequested,
+                ) => Some(MainMessage::CloseWindow),
+                _ => None,
+            },
+        );
+
+        let pull = match &self.pull {
+            Some(pull) => {
+                pull::pull_model(pull.model.clone())
+                    .map(MainMessage::PullProgress)
             }
-            _ => None,
-        })
+            None => Subscription::none(),
+        };
+
+        let color_scheme = system::color_scheme_changes()
+            .map(MainMessage::ColorSchemeChanged);
+
+        Subscription::batch([
+            shortcuts,
+            zoom_shortcuts,
+            health_checks,
+            window_events,
+            instance::activations()
+                .map(|()| MainMessage::ShowWindow),
+            pull,
+            color_scheme,
+        ])
+    }
+
+    fn scale_factor(&self) -> f64 {
+        fonts::clamp_zoom(self.config_settings.zoom)
     }
 
     fn theme(&self) -> Self::Theme {
@@ -0,0 +1,71 @@
+use iced::{
+    futures::{never::Never, SinkExt},
+    subscription, Subscription,
+};
+
+use crate::{
+    api::{self, PullStatus},
+    t,
+};
+
+/// Update of a model download
+#[derive(Debug, Clone)]
+pub enum PullEvent {
+    Progress(PullStatus),
+    Finished(Result<(), String>),
+}
+
+/// Download in progress, as shown to the user
+#[derive(Debug, Clone)]
+pub struct PullState {
+    pub model: String,
+    pub status: String,
+    /// Share of the current layer downloaded, from 0 to 100
+    pub percent: f32,
+}
+
+impl PullState {
+    pub fn new(model: String) -> Self {
+        Self {
+            model,
+            status: t!("model-pull-starting"),
+            percent: 0.,
+        }
+    }
+
+    pub fn update(&mut self, status: PullStatus) {
+        self.percent = if status.total > 0 {
+            status.completed as f32 / status.total as f32
+                * 100.
+        } else {
+            0.
+        };
+        self.status = status.status;
+    }
+}
+
+/// Download a model for as long as the subscription is kept
+pub fn pull_model(
+    model: String,
+) -> Subscription<PullEvent> {
+    subscription::channel(
+        ("pull-model", model.clone()),
+        100,
+        move |mut output| async move {
+            let mut progress = output.clone();
+            let result = api::pull(&model, |status| {
+                // Skipped progress steps are not worth
+                // waiting for
+                let _ = progress
+                    .try_send(PullEvent::Progress(status));
+            })
+            .await;
+
+            let _ = output
+                .send(PullEvent::Finished(result))
+                .await;
+
+            std::future::pending::<Never>().await
+        },
+    )
+}
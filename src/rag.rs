@@ -0,0 +1,558 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use iced::futures::lock::Mutex;
+use lazy_static::lazy_static;
+
+use crate::{
+    ai::embed, config::ApplicationSettings, debug, t,
+};
+
+const DOCUMENTS_INDEX_FILE_NAME: &str =
+    "documents_index.json";
+const INDEXED_EXTENSIONS: [&str; 3] =
+    ["md", "markdown", "txt"];
+/// Characters above which a chunk is closed
+const CHUNK_SIZE: usize = 1000;
+/// Lines repeated at the start of the next chunk
+const CHUNK_OVERLAP_LINES: usize = 2;
+/// Cosine similarity below which a chunk is considered
+/// unrelated to the prompt and left out
+const MIN_SIMILARITY: f32 = 0.5;
+
+lazy_static! {
+    /// Index loaded from disk, kept around between prompts
+    static ref DOCUMENTS_INDEX: Mutex<Option<DocumentsIndex>> =
+        Mutex::new(None);
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Default,
+)]
+struct DocumentsIndex {
+    embedding_model: String,
+    /// Documents directory the index was built from
+    #[serde(default)]
+    directory: String,
+    files: Vec<IndexedFile>,
+}
+
+impl DocumentsIndex {
+    /// Whether the index was built from the documents and
+    /// with the model of the settings
+    fn matches(
+        &self,
+        settings: &ApplicationSettings,
+    ) -> bool {
+        self.embedding_model == settings.embedding_model
+            && documents_dir(settings)
+                == Some(self.directory.as_str())
+    }
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+struct IndexedFile {
+    path: PathBuf,
+    modified: u64,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(
+    serde::Deserialize, serde::Serialize, Debug, Clone,
+)]
+struct Chunk {
+    start_line: usize,
+    end_line: usize,
+    text: String,
+    embedding: Vec<f32>,
+}
+
+/// Where a passage given to the AI comes from
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    PartialEq,
+)]
+pub struct Citation {
+    pub path: PathBuf,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl std::fmt::Display for Citation {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}-{}",
+            self.path.display(),
+            self.start_line,
+            self.end_line
+        )
+    }
+}
+
+/// Passage of the documents relevant to a prompt
+#[derive(Debug, Clone)]
+pub struct RetrievedChunk {
+    pub citation: Citation,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexSummary {
+    pub files: usize,
+    pub chunks: usize,
+    pub embedded: usize,
+}
+
+impl std::fmt::Display for IndexSummary {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(&t!(
+            "documents-indexed",
+            chunks = self.chunks,
+            files = self.files,
+            new = self.embedded
+        ))
+    }
+}
+
+/// State of the documents shown in the settings,
+/// translated when shown
+#[derive(Debug, Clone)]
+pub enum DocumentsStatus {
+    Indexing,
+    Indexed(IndexSummary),
+    IndexingFailed(String),
+    /// The last reply was given without the documents
+    SearchFailed(String),
+}
+
+impl std::fmt::Display for DocumentsStatus {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Indexing => {
+                f.write_str(&t!("documents-indexing"))
+            }
+            Self::Indexed(summary) => summary.fmt(f),
+            Self::IndexingFailed(err) => f.write_str(&t!(
+                "documents-indexing-failed",
+                error = err
+            )),
+            Self::SearchFailed(err) => f.write_str(&t!(
+                "documents-search-failed",
+                error = err
+            )),
+        }
+    }
+}
+
+/// Index the text and Markdown files of the documents
+/// directory, embedding only the files changed since the
+/// last run
+pub async fn index_documents(
+    settings: ApplicationSettings,
+) -> Result<IndexSummary, String> {
+    let directory = documents_dir(&settings)
+        .ok_or_else(|| t!("documents-no-directory"))?;
+
+    let previous = load_index()
+        .filter(|index| index.matches(&settings))
+        .unwrap_or_default();
+
+    let mut paths = vec![];
+    collect_files(Path::new(directory), &mut paths)?;
+
+    let mut index = DocumentsIndex {
+        embedding_model: settings.embedding_model.clone(),
+        directory: directory.to_string(),
+        files: vec![],
+    };
+    let mut embedded = 0;
+
+    for path in paths {
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|time| {
+                time.duration_since(UNIX_EPOCH).ok()
+            })
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        if let Some(file) =
+            previous.files.iter().find(|file| {
+                file.path == path
+                    && file.modified == modified
+            })
+        {
+            index.files.push(file.clone());
+            continue;
+        }
+
+        let Ok(contents) = fs::read_to_string(&path) else {
+            debug!(&path);
+            continue;
+        };
+
+        let mut chunks = vec![];
+        for (start_line, end_line, text) in
+            split_chunks(&contents)
+        {
+            let embedding = embed(
+                text.clone(),
+                &settings.embedding_model,
+            )
+            .await?;
+            embedded += 1;
+            chunks.push(Chunk {
+                start_line,
+                end_line,
+                text,
+                embedding,
+            });
+        }
+
+        index.files.push(IndexedFile {
+            path,
+            modified,
+            chunks,
+        });
+    }
+
+    let summary = IndexSummary {
+        files: index.files.len(),
+        chunks: index
+            .files
+            .iter()
+            .map(|f| f.chunks.len())
+            .sum(),
+        embedded,
+    };
+
+    let serialized = serde_json::to_string(&index)
+        .map_err(|e| e.to_string())?;
+    fs::write(DOCUMENTS_INDEX_FILE_NAME, serialized)
+        .map_err(|e| e.to_string())?;
+    *DOCUMENTS_INDEX.lock().await = Some(index);
+
+    Ok(summary)
+}
+
+/// Passages of the indexed documents closest to the query,
+/// nothing being returned when no index was built
+pub async fn retrieve(
+    query: &str,
+    settings: &ApplicationSettings,
+) -> Result<Vec<RetrievedChunk>, String> {
+    let mut cached = DOCUMENTS_INDEX.lock().await;
+    if cached.is_none() {
+        *cached = load_index();
+    }
+    let Some(index) = cached
+        .as_ref()
+        .filter(|index| index.matches(settings))
+    else {
+        return Ok(vec![]);
+    };
+
+    let query =
+        embed(query.to_string(), &settings.embedding_model)
+            .await?;
+
+    let mut scored = index
+        .files
+        .iter()
+        .flat_map(|file| {
+            file.chunks
+                .iter()
+                .map(move |chunk| (file, chunk))
+        })
+        .map(|(file, chunk)| {
+            (
+                cosine_similarity(&query, &chunk.embedding),
+                file,
+                chunk,
+            )
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    Ok(scored
+        .into_iter()
+        .take_while(|(score, ..)| *score >= MIN_SIMILARITY)
+        .take(settings.documents_top_k)
+        .map(|(_, file, chunk)| RetrievedChunk {
+            citation: Citation {
+                path: file.path.clone(),
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+            },
+            text: chunk.text.clone(),
+        })
+        .collect())
+}
+
+/// Drop the index kept in memory once the documents
+/// directory changed. While a prompt holds it, `retrieve`
+/// leaves it out anyway as it no longer matches
+pub fn forget_index() {
+    if let Some(mut index) = DOCUMENTS_INDEX.try_lock() {
+        *index = None;
+    }
+}
+
+/// Most tokens the excerpts sent along with a prompt may
+/// take, counting about four characters per token
+pub fn excerpts_tokens(
+    settings: &ApplicationSettings,
+) -> usize {
+    if documents_dir(settings).is_some() {
+        settings.documents_top_k * CHUNK_SIZE / 4
+    } else {
+        0
+    }
+}
+
+fn documents_dir(
+    settings: &ApplicationSettings,
+) -> Option<&str> {
+    settings
+        .documents_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+}
+
+fn load_index() -> Option<DocumentsIndex> {
+    fs::read_to_string(DOCUMENTS_INDEX_FILE_NAME)
+        .ok()
+        .and_then(|contents| {
+            serde_json::from_str(&contents).ok()
+        })
+}
+
+fn collect_files(
+    directory: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let entries =
+        fs::read_dir(directory).map_err(|err| {
+            t!(
+                "error-cannot-read",
+                path = directory.display().to_string(),
+                error = err.to_string()
+            )
+        })?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_hidden = entry
+            .file_name()
+            .to_string_lossy()
+            .starts_with('.');
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if is_hidden {
+            continue;
+        } else if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_dir() {
+            // Links to folders are skipped, as one to an
+            // ancestor would be followed forever
+            continue;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                INDEXED_EXTENSIONS
+                    .contains(&ext.to_lowercase().as_str())
+            })
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Split a text into chunks of whole lines, returning the
+/// 1-based first and last line of each
+fn split_chunks(
+    contents: &str,
+) -> Vec<(usize, usize, String)> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let mut chunks = vec![];
+    let mut start = 0;
+
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len()
+            && (size < CHUNK_SIZE || end == start)
+        {
+            size += lines[end].len() + 1;
+            end += 1;
+        }
+
+        let text = lines[start..end].join("\n");
+        if !text.trim().is_empty() {
+            chunks.push((start + 1, end, text));
+        }
+
+        if end >= lines.len() {
+            break;
+        }
+        start = end
+            .saturating_sub(CHUNK_OVERLAP_LINES)
+            .max(start + 1);
+    }
+
+    chunks
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot =
+        a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    let norm_a =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b =
+        b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0. || norm_b == 0. {
+        0.
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_overlap_by_whole_lines() {
+        let line = "x".repeat(CHUNK_SIZE / 4);
+        let contents = (1..=10)
+            .map(|n| format!("{n} {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let chunks = split_chunks(&contents);
+        let lines = chunks
+            .iter()
+            .map(|(start, end, _)| (*start, *end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [(1, 4), (3, 6), (5, 8), (7, 10)]
+        );
+        assert!(chunks[1].2.starts_with("3 "));
+    }
+
+    #[test]
+    fn long_lines_make_a_chunk_each() {
+        let long = "x".repeat(CHUNK_SIZE * 3);
+        let contents = format!("{long}\n{long}\n\n");
+
+        let chunks = split_chunks(&contents);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|(start, end, _)| (*start, *end))
+                .collect::<Vec<_>>(),
+            [(1, 1), (2, 2)]
+        );
+        assert_eq!(chunks[0].2, long);
+        assert!(split_chunks(" \n\n").is_empty());
+    }
+
+    #[test]
+    fn cosine_similarity_of_zero_vectors_is_zero() {
+        assert_eq!(
+            cosine_similarity(&[0., 0.], &[1., 2.]),
+            0.
+        );
+        assert_eq!(cosine_similarity(&[], &[]), 0.);
+        assert_eq!(
+            cosine_similarity(&[1., 0.], &[2., 0.]),
+            1.
+        );
+        assert_eq!(
+            cosine_similarity(&[1., 0.], &[0., 3.]),
+            0.
+        );
+    }
+
+    #[test]
+    fn index_is_only_used_for_its_directory_and_model() {
+        let index = DocumentsIndex {
+            embedding_model: "nomic".to_string(),
+            directory: "/notes".to_string(),
+            files: vec![],
+        };
+        let settings = |directory: &str, model: &str| {
+            ApplicationSettings {
+                documents_dir: Some(directory.to_string()),
+                embedding_model: model.to_string(),
+                ..ApplicationSettings::default()
+            }
+        };
+
+        assert!(
+            index.matches(&settings(" /notes ", "nomic"))
+        );
+        assert!(
+            !index.matches(&settings("/other", "nomic"))
+        );
+        assert!(
+            !index.matches(&settings("/notes", "other"))
+        );
+        assert!(!index.matches(&settings("", "nomic")));
+    }
+
+    #[test]
+    fn links_to_folders_are_not_followed() {
+        let root = std::env::temp_dir().join(format!(
+            "ai-overlay-documents-{}",
+            std::process::id()
+        ));
+        let nested = root.join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("note.md"), "Note").unwrap();
+        fs::write(nested.join("image.png"), "").unwrap();
+        std::os::unix::fs::symlink(
+            &root,
+            nested.join("loop"),
+        )
+        .unwrap();
+
+        let mut files = vec![];
+        let result = collect_files(&root, &mut files);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(files, [nested.join("note.md")]);
+    }
+}
@@ -0,0 +1,397 @@
+use std::{collections::HashMap, ops::Range};
+
+use chrono::{Local, TimeZone};
+
+use crate::{chat::Role, conversations::ConversationStore};
+
+/// Characters kept around the first match of a result
+const SNIPPET_CONTEXT: usize = 60;
+const MAX_RESULTS: usize = 50;
+
+/// A message of a stored conversation, as indexed
+#[derive(Debug, Clone)]
+struct Document {
+    conversation: u64,
+    message: usize,
+    role: Role,
+    model: Option<String>,
+    date: u64,
+    content: String,
+}
+
+/// Inverted index of the words of every stored message,
+/// inactive branches included
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    documents: Vec<Document>,
+    postings: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub conversation: u64,
+    pub message: usize,
+    pub title: String,
+    pub role: Role,
+    pub model: Option<String>,
+    pub date: String,
+    pub snippet: String,
+    /// Byte ranges of the snippet matching the query
+    pub matches: Vec<Range<usize>>,
+}
+
+impl SearchIndex {
+    pub fn build(store: &ConversationStore) -> Self {
+        let mut index = Self::default();
+
+        for saved in store.sorted() {
+            for (id, message) in
+                saved.conversation.all_messages()
+            {
+                let document = index.documents.len();
+
+                for word in words(&message.content) {
+                    let postings = index
+                        .postings
+                        .entry(word)
+                        .or_default();
+                    if postings.last() != Some(&document) {
+                        postings.push(document);
+                    }
+                }
+
+                index.documents.push(Document {
+                    conversation: saved.id,
+                    message: id,
+                    role: message.role,
+                    model: message.model.clone(),
+                    date: if message.created_at > 0 {
+                        message.created_at
+                    } else {
+                        saved.updated_at
+                    },
+                    content: message.content.clone(),
+                });
+            }
+        }
+
+        index
+    }
+
+    /// Messages containing every word of the query, the last
+    /// one being matched as a prefix. A `model:name` term
+    /// restricts results to replies of that model
+    pub fn search(
+        &self,
+        store: &ConversationStore,
+        query: &str,
+    ) -> Vec<SearchHit> {
+        let (filters, terms): (Vec<&str>, Vec<&str>) =
+            query.split_whitespace().partition(|term| {
+                term.starts_with("model:")
+            });
+        let model_filter = filters.last().map(|filter| {
+            filter["model:".len()..].to_lowercase()
+        });
+        let terms = terms
+            .iter()
+            .flat_map(|term| words(term))
+            .collect::<Vec<_>>();
+
+        if terms.is_empty() {
+            return vec![];
+        }
+
+        let mut candidates: Option<Vec<usize>> = None;
+        for (position, term) in terms.iter().enumerate() {
+            let is_prefix = position == terms.len() - 1;
+            let mut documents = self
+                .postings
+                .iter()
+                .filter(|(word, _)| {
+                    if is_prefix {
+                        word.starts_with(term.as_str())
+                    } else {
+                        *word == term
+                    }
+                })
+                .flat_map(|(_, documents)| {
+                    documents.clone()
+                })
+                .collect::<Vec<_>>();
+            documents.sort_unstable();
+            documents.dedup();
+
+            candidates = Some(match candidates {
+                Some(previous) => previous
+                    .into_iter()
+                    .filter(|d| {
+                        documents.binary_search(d).is_ok()
+                    })
+                    .collect(),
+                None => documents,
+            });
+        }
+
+        let mut hits = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .map(|document| &self.documents[document])
+            .filter(|document| {
+                model_filter.as_ref().is_none_or(|filter| {
+                    document.model.as_ref().is_some_and(
+                        |model| {
+                            model
+                                .to_lowercase()
+                                .contains(filter)
+                        },
+                    )
+                })
+            })
+            .filter_map(|document| {
+                let saved =
+                    store.get(document.conversation)?;
+                let (snippet, matches) =
+                    snippet(&document.content, &terms);
+
+                Some((
+                    document.date,
+                    SearchHit {
+                        conversation: document.conversation,
+                        message: document.message,
+                        title: saved.display_title(),
+                        role: document.role,
+                        model: document.model.clone(),
+                        date: format_date(document.date),
+                        snippet,
+                        matches,
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by_key(|(date, _)| {
+            std::cmp::Reverse(*date)
+        });
+        hits.into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, hit)| hit)
+            .collect()
+    }
+}
+
+/// Lowercase words of a text, as stored in the index
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Part of the content around the first match, with the
+/// ranges of every word starting with a query term
+fn snippet(
+    content: &str,
+    terms: &[String],
+) -> (String, Vec<Range<usize>>) {
+    let content = content.replace(['\n', '\t'], " ");
+    let word_ranges = content
+        .char_indices()
+        .filter(|(i, c)| {
+            c.is_alphanumeric()
+                && !content[..*i]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric)
+        })
+        .map(|(start, _)| {
+            let end = content[start..]
+                .find(|c: char| !c.is_alphanumeric())
+                .map_or(content.len(), |end| start + end);
+            start..end
+        })
+        .filter(|range| {
+            let word =
+                content[range.clone()].to_lowercase();
+            terms.iter().any(|term| word.starts_with(term))
+        })
+        .collect::<Vec<_>>();
+
+    let first = word_ranges.first().map_or(0, |r| r.start);
+    let start = floor_char_boundary(
+        &content,
+        first.saturating_sub(SNIPPET_CONTEXT),
+    );
+    let end = floor_char_boundary(
+        &content,
+        (first + SNIPPET_CONTEXT * 2).min(content.len()),
+    );
+
+    let prefix = if start > 0 { "..." } else { "" };
+    let suffix =
+        if end < content.len() { "..." } else { "" };
+    let offset = prefix.len();
+
+    let matches = word_ranges
+        .into_iter()
+        .filter(|range| {
+            range.start >= start && range.end <= end
+        })
+        .map(|range| {
+            range.start - start + offset
+                ..range.end - start + offset
+        })
+        .collect();
+
+    (
+        format!("{prefix}{}{suffix}", &content[start..end]),
+        matches,
+    )
+}
+
+fn floor_char_boundary(
+    text: &str,
+    mut index: usize,
+) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+pub fn format_date(timestamp: u64) -> String {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|date| {
+            date.format("%Y-%m-%d %H:%M").to_string()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(
+        messages: &[(Role, &str)],
+    ) -> ConversationStore {
+        let mut store = ConversationStore::default();
+        for (role, content) in messages {
+            store
+                .current_mut()
+                .push(*role, content.to_string());
+        }
+        store
+    }
+
+    #[test]
+    fn words_are_lowercase_and_split_on_punctuation() {
+        assert_eq!(
+            words("Hello, World! it's v2.0")
+                .collect::<Vec<_>>(),
+            ["hello", "world", "it", "s", "v2", "0"]
+        );
+        assert_eq!(words(" -- ").count(), 0);
+    }
+
+    #[test]
+    fn every_term_must_match_the_last_as_a_prefix() {
+        let store = store(&[
+            (Role::User, "How do I sort a vector in Rust?"),
+            (
+                Role::Assistant,
+                "Call sort_unstable on the vector",
+            ),
+            (Role::User, "And a hash map?"),
+        ]);
+        let index = SearchIndex::build(&store);
+
+        assert_eq!(
+            index.search(&store, "vector sort").len(),
+            2
+        );
+        assert_eq!(
+            index.search(&store, "rust vec").len(),
+            1
+        );
+        assert!(index
+            .search(&store, "vec rust")
+            .is_empty());
+        assert!(index.search(&store, "python").is_empty());
+        assert!(index.search(&store, "  ").is_empty());
+    }
+
+    #[test]
+    fn model_filter_keeps_replies_of_that_model() {
+        let store = store(&[
+            (Role::User, "Hello"),
+            (Role::Assistant, "Hello to you"),
+        ]);
+        let mut index = SearchIndex::build(&store);
+        index.documents[1].model = Some("Llama3:8b".into());
+
+        let hits =
+            index.search(&store, "hello model:llama3");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].role, Role::Assistant);
+        assert!(index
+            .search(&store, "hello model:mistral")
+            .is_empty());
+    }
+
+    #[test]
+    fn newest_messages_rank_first() {
+        let store = store(&[
+            (Role::User, "first note"),
+            (Role::User, "second note"),
+            (Role::User, "third note"),
+        ]);
+        let mut index = SearchIndex::build(&store);
+        for (date, document) in [20, 30, 10]
+            .into_iter()
+            .zip(&mut index.documents)
+        {
+            document.date = date;
+        }
+
+        let hits = index.search(&store, "note");
+        assert_eq!(
+            hits.iter()
+                .map(|hit| hit.snippet.as_str())
+                .collect::<Vec<_>>(),
+            ["second note", "first note", "third note"]
+        );
+    }
+
+    #[test]
+    fn snippet_marks_matching_words() {
+        let (snippet, matches) = snippet(
+            "Sorting\nis sorted",
+            &["sort".to_string()],
+        );
+
+        assert_eq!(snippet, "Sorting is sorted");
+        assert_eq!(
+            matches
+                .into_iter()
+                .map(|range| &snippet[range])
+                .collect::<Vec<_>>(),
+            ["Sorting", "sorted"]
+        );
+    }
+
+    #[test]
+    fn long_snippets_are_cut_around_the_match() {
+        let content = format!(
+            "{}needle{}",
+            "é ".repeat(50),
+            " x".repeat(100)
+        );
+        let (snippet, matches) =
+            snippet(&content, &["needle".to_string()]);
+
+        assert!(snippet.starts_with("..."));
+        assert!(snippet.ends_with("..."));
+        assert_eq!(&snippet[matches[0].clone()], "needle");
+    }
+}
@@ -0,0 +1,369 @@
+use serde_json::Value;
+
+use crate::{config::ApplicationSettings, t};
+
+/// Schema the replies have to match in JSON mode, written
+/// inline in the settings or in the file they point to
+pub fn load_schema(
+    settings: &ApplicationSettings,
+) -> Result<Option<Value>, String> {
+    let Some(schema) = settings
+        .json_schema
+        .as_ref()
+        .map(|schema| schema.trim())
+        .filter(|schema| !schema.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    let contents = if schema.starts_with('{') {
+        schema.to_string()
+    } else {
+        std::fs::read_to_string(schema).map_err(|err| {
+            t!(
+                "json-schema-cannot-read",
+                path = schema,
+                error = err.to_string()
+            )
+        })?
+    };
+
+    serde_json::from_str(&contents).map(Some).map_err(
+        |err| {
+            t!(
+                "json-schema-invalid",
+                error = err.to_string()
+            )
+        },
+    )
+}
+
+/// Problems found in a reply, empty when it is JSON matching
+/// the schema
+pub fn validate_reply(
+    reply: &str,
+    schema: Option<&Value>,
+) -> Vec<String> {
+    match serde_json::from_str::<Value>(reply) {
+        Ok(value) => {
+            let mut errors = vec![];
+            if let Some(schema) = schema {
+                check(&value, schema, "", &mut errors);
+            }
+            errors
+        }
+        Err(err) => vec![format!("not valid JSON: {err}")],
+    }
+}
+
+/// Check a value against the commonly used subset of JSON
+/// Schema: `type`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, `items` and the length and range
+/// bounds
+fn check(
+    value: &Value,
+    schema: &Value,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let at = if path.is_empty() { "/" } else { path };
+    let keyword = |name: &str| schema.get(name);
+
+    if let Some(expected) = keyword("type") {
+        let types = match expected {
+            Value::Array(types) => types.iter().collect(),
+            other => vec![other],
+        };
+        if !types.iter().any(|t| {
+            t.as_str().is_some_and(|t| has_type(value, t))
+        }) {
+            errors.push(format!(
+                "{at}: expected type {expected}"
+            ));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = keyword("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{at}: must be one of {}",
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+    if let Some(constant) = keyword("const") {
+        if value != constant {
+            errors
+                .push(format!("{at}: must be {constant}"));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            if let Some(Value::Array(required)) =
+                keyword("required")
+            {
+                for name in required
+                    .iter()
+                    .filter_map(Value::as_str)
+                {
+                    if !object.contains_key(name) {
+                        errors.push(format!(
+                            "{at}: missing property `{name}`"
+                        ));
+                    }
+                }
+            }
+
+            let properties = keyword("properties")
+                .and_then(Value::as_object);
+            for (name, property) in object {
+                let property_path =
+                    format!("{path}/{name}");
+                match (
+                    properties
+                        .and_then(|props| props.get(name)),
+                    keyword("additionalProperties"),
+                ) {
+                    (Some(schema), _) => check(
+                        property,
+                        schema,
+                        &property_path,
+                        errors,
+                    ),
+                    (None, Some(Value::Bool(false))) => {
+                        errors.push(format!(
+                            "{at}: unexpected property `{name}`"
+                        ))
+                    }
+                    (None, Some(schema))
+                        if schema.is_object() =>
+                    {
+                        check(
+                            property,
+                            schema,
+                            &property_path,
+                            errors,
+                        )
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            check_bounds(
+                items.len() as f64,
+                schema,
+                ("minItems", "maxItems"),
+                &format!("{at}: number of items"),
+                errors,
+            );
+            if let Some(schema) = keyword("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(
+                        item,
+                        schema,
+                        &format!("{path}/{i}"),
+                        errors,
+                    );
+                }
+            }
+        }
+        Value::String(string) => check_bounds(
+            string.chars().count() as f64,
+            schema,
+            ("minLength", "maxLength"),
+            &format!("{at}: length"),
+            errors,
+        ),
+        Value::Number(number) => check_bounds(
+            number.as_f64().unwrap_or_default(),
+            schema,
+            ("minimum", "maximum"),
+            at,
+            errors,
+        ),
+        _ => {}
+    }
+}
+
+fn check_bounds(
+    actual: f64,
+    schema: &Value,
+    (min, max): (&str, &str),
+    subject: &str,
+    errors: &mut Vec<String>,
+) {
+    let bound =
+        |name| schema.get(name).and_then(Value::as_f64);
+
+    if let Some(min) =
+        bound(min).filter(|min| actual < *min)
+    {
+        errors.push(format!(
+            "{subject} must be at least {min}"
+        ));
+    }
+    if let Some(max) =
+        bound(max).filter(|max| actual > *max)
+    {
+        errors.push(format!(
+            "{subject} must be at most {max}"
+        ));
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64()
+                || value.is_u64()
+                || value
+                    .as_f64()
+                    .is_some_and(|n| n.fract() == 0.)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn person_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": {
+                    "type": "integer",
+                    "minimum": 0,
+                    "maximum": 150
+                },
+                "role": { "enum": ["admin", "user"] },
+                "tags": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "maxItems": 2
+                }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn matching_reply_has_no_errors() {
+        let reply = r#"{"name": "Ada", "age": 36,
+            "role": "admin", "tags": ["math"]}"#;
+
+        assert!(validate_reply(
+            reply,
+            Some(&person_schema())
+        )
+        .is_empty());
+        assert!(validate_reply("[1, 2]", None).is_empty());
+    }
+
+    #[test]
+    fn invalid_json_is_reported() {
+        assert_eq!(
+            validate_reply("{name: 1}", None).len(),
+            1
+        );
+        assert_eq!(
+            validate_reply("", Some(&person_schema()))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn every_mismatch_is_reported_with_its_path() {
+        let reply = r#"{"name": "", "age": 36.5,
+            "role": "guest", "tags": ["a", 2, "c"],
+            "email": "ada@example.com"}"#;
+        let errors =
+            validate_reply(reply, Some(&person_schema()));
+        let paths = errors
+            .iter()
+            .map(|error| error.split(':').next().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 6, "{errors:?}");
+        for path in [
+            "/name", "/age", "/role", "/tags", "/tags/1",
+            "/",
+        ] {
+            assert!(paths.contains(&path), "{errors:?}");
+        }
+    }
+
+    #[test]
+    fn missing_properties_and_bounds() {
+        let errors = validate_reply(
+            r#"{"age": 200}"#,
+            Some(&person_schema()),
+        );
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].contains("`name`"));
+        assert!(errors[1].starts_with("/age"));
+    }
+
+    #[test]
+    fn type_lists_and_integers() {
+        let schema = json!({ "type": ["integer", "null"] });
+
+        assert!(
+            validate_reply("3", Some(&schema)).is_empty()
+        );
+        assert!(
+            validate_reply("3.0", Some(&schema)).is_empty()
+        );
+        assert!(validate_reply("null", Some(&schema))
+            .is_empty());
+        assert_eq!(
+            validate_reply("3.5", Some(&schema)).len(),
+            1
+        );
+        assert_eq!(
+            validate_reply(r#""3""#, Some(&schema)).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn inline_schema_is_loaded_from_the_settings() {
+        let settings = ApplicationSettings {
+            json_schema: Some(
+                r#" {"type": "object"} "#.to_string(),
+            ),
+            ..ApplicationSettings::default()
+        };
+        assert_eq!(
+            load_schema(&settings),
+            Ok(Some(json!({ "type": "object" })))
+        );
+
+        let settings = ApplicationSettings {
+            json_schema: Some("{ not json".to_string()),
+            ..ApplicationSettings::default()
+        };
+        assert!(load_schema(&settings).is_err());
+
+        assert_eq!(
+            load_schema(&ApplicationSettings::default()),
+            Ok(None)
+        );
+    }
+}
@@ -1,23 +1,27 @@
 use iced::{widget::button, Border, Shadow};
 
+use super::theme::current_theme;
+
 pub struct TransparentButton;
 impl button::StyleSheet for TransparentButton {
     type Style = iced::Theme;
 
     fn active(
         &self,
-        style: &Self::Style,
+        _style: &Self::Style,
     ) -> button::Appearance {
+        let theme = current_theme();
+
         button::Appearance {
             shadow_offset: iced::Vector::default(),
             background: None,
             border: Border {
                 radius: 0.0.into(),
                 width: 0.,
-                color: style.palette().primary,
+                color: theme.primary,
             },
             shadow: Shadow::default(),
-            text_color: style.palette().text,
+            text_color: theme.text,
         }
     }
 
@@ -64,3 +68,14 @@ impl button::StyleSheet for TransparentButton {
 pub fn get_btn_transparent_style() -> iced::theme::Button {
     iced::theme::Button::Custom(Box::new(TransparentButton))
 }
+
+/// Ring around the widget focused with the keyboard
+pub fn get_focus_ring() -> Border {
+    let theme = current_theme();
+
+    Border {
+        color: theme.accent,
+        width: 2.0,
+        radius: (theme.border_radius / 2.0).into(),
+    }
+}
@@ -2,7 +2,9 @@ use iced::{
     border::Radius, widget::container, Border, Color,
 };
 
-use super::{colors_and_themes, CustomTheme};
+use super::{
+    colors_and_themes, theme::current_theme, CustomTheme,
+};
 
 impl container::StyleSheet for CustomTheme {
     type Style = iced::Theme;
@@ -11,16 +13,18 @@ impl container::StyleSheet for CustomTheme {
         &self,
         _style: &Self::Style,
     ) -> container::Appearance {
+        let theme = current_theme();
+
         container::Appearance {
             border: Border {
-                color: Color::BLACK,
+                color: theme.border_color,
                 width: 1.0,
-                radius: Radius::from(16.0),
+                radius: Radius::from(theme.border_radius),
             },
             background: Some(
                 colors_and_themes::get_background(),
             ),
-            text_color: Some(_style.palette().text),
+            text_color: Some(theme.text),
             ..Default::default()
         }
     }
@@ -29,3 +33,32 @@ impl container::StyleSheet for CustomTheme {
 pub fn get_container_style() -> iced::theme::Container {
     iced::theme::Container::Custom(Box::new(CustomTheme))
 }
+
+/// Round dot of a single color, e.g. for a status
+pub struct StatusDot(Color);
+
+impl container::StyleSheet for StatusDot {
+    type Style = iced::Theme;
+
+    fn appearance(
+        &self,
+        _style: &Self::Style,
+    ) -> container::Appearance {
+        container::Appearance {
+            border: Border {
+                radius: Radius::from(5.0),
+                ..Default::default()
+            },
+            background: Some(self.0.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn get_status_dot_style(
+    color: Color,
+) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(StatusDot(
+        color,
+    )))
+}
@@ -1,55 +1,39 @@
-use iced::{color, theme::Palette, Theme};
+use iced::{theme::Palette, Theme};
 
-use self::system::system_theme_is_dark;
+use self::theme::current_theme;
 
 pub mod application;
 pub mod button;
 pub mod container;
 pub mod system;
 pub mod text_input;
+pub mod theme;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct CustomTheme;
 mod colors_and_themes {
     use iced::{Background, Color};
 
-    use super::system::system_theme_is_dark;
+    use super::theme::current_theme;
 
     pub fn get_background() -> Background {
-        Background::Color(if system_theme_is_dark() {
-            Color::BLACK
-        } else {
-            Color::WHITE
-        })
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum AppTheme {
-    Light,
-    Dark,
-}
+        let theme = current_theme();
 
-pub fn get_app_theme() -> AppTheme {
-    if system_theme_is_dark() {
-        AppTheme::Dark
-    } else {
-        AppTheme::Light
+        Background::Color(Color {
+            a: theme.opacity,
+            ..theme.background
+        })
     }
 }
 
 pub const PADDING_SIZE: u16 = 8;
 
 pub fn get_theme_for_main_window() -> Theme {
-    if system_theme_is_dark() {
-        // Oxacarbon without background
-        iced::Theme::Oxocarbon
-    } else {
-        // TokyoNightLight
-        iced::Theme::TokyoNightLight
-    }
+    let theme = current_theme();
+
+    Theme::custom(theme.name.clone(), theme.palette())
 }
 
 pub fn get_palette_for_main_window() -> Palette {
-    get_theme_for_main_window().palette()
+    current_theme().palette()
 }
@@ -1,42 +1,411 @@
-use std::{env, process::Command};
+use std::{
+    env, fs, path::PathBuf, process::Command, sync::RwLock,
+    time::Duration,
+};
+
+use iced::{
+    futures::{SinkExt, StreamExt},
+    subscription, Subscription,
+};
+use zbus::{
+    message::Type,
+    zvariant::{OwnedValue, Value},
+    MatchRule, MessageStream,
+};
+
+const PORTAL_SERVICE: &str =
+    "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SETTINGS_INTERFACE: &str =
+    "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str =
+    "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+/// Limit for the portal to answer, it may be started on
+/// demand
+const PORTAL_TIMEOUT: Duration = Duration::from_secs(1);
+/// Interval between two checks of the settings files when
+/// the portal cannot tell about changes
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Scheme detected last, so styles do not query the desktop
+/// each time they are drawn
+static COLOR_SCHEME: RwLock<Option<ColorScheme>> =
+    RwLock::new(None);
+
+/// Color scheme preferred by the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    Dark,
+    #[default]
+    Light,
+}
 
 enum DesktopEnvironment {
     Gnome,
+    Kde,
     Unknown,
 }
 
 impl From<String> for DesktopEnvironment {
     fn from(de: String) -> Self {
-        match de.as_str() {
-            "GNOME" => DesktopEnvironment::Gnome,
-            _ => DesktopEnvironment::Unknown,
+        // e.g. `ubuntu:GNOME`
+        let names = de.split(':').collect::<Vec<_>>();
+        if names.contains(&"GNOME") {
+            DesktopEnvironment::Gnome
+        } else if names.contains(&"KDE") {
+            DesktopEnvironment::Kde
+        } else {
+            DesktopEnvironment::Unknown
         }
     }
 }
 
-/// Check if system theme is dark
-pub fn system_theme_is_dark() -> bool {
+/// Cached color scheme, detected on first use
+pub fn color_scheme() -> ColorScheme {
+    if let Some(scheme) =
+        COLOR_SCHEME.read().ok().and_then(|cache| *cache)
+    {
+        return scheme;
+    }
+
+    let scheme = detect_color_scheme();
+    set_color_scheme(scheme);
+    scheme
+}
+
+pub fn set_color_scheme(scheme: ColorScheme) {
+    if let Ok(mut cache) = COLOR_SCHEME.write() {
+        *cache = Some(scheme);
+    }
+}
+
+/// Ask the desktop portal first, then read the settings files
+/// of the desktop, light being the default when nothing tells
+pub fn detect_color_scheme() -> ColorScheme {
+    portal_color_scheme()
+        .or_else(desktop_color_scheme)
+        .unwrap_or_default()
+}
+
+/// Every change of the color scheme, told by the portal or
+/// else noticed by polling the settings files
+pub fn color_scheme_changes() -> Subscription<ColorScheme> {
+    subscription::channel(
+        "color-scheme-changes",
+        10,
+        |mut output| async move {
+            if let Err(err) =
+                watch_portal(&mut output).await
+            {
+                crate::debug!(err);
+            }
+
+            let mut last = None;
+            loop {
+                let scheme = tokio::task::spawn_blocking(
+                    detect_color_scheme,
+                )
+                .await
+                .unwrap_or_default();
+                if last.is_some_and(|last| last != scheme) {
+                    let _ = output.send(scheme).await;
+                }
+                last = Some(scheme);
+
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        },
+    )
+}
+
+/// Forward the `SettingChanged` signals of the portal about
+/// the color scheme, only returning if it cannot be listened
+/// to
+async fn watch_portal(
+    output: &mut iced::futures::channel::mpsc::Sender<
+        ColorScheme,
+    >,
+) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface(SETTINGS_INTERFACE)?
+        .member("SettingChanged")?
+        .path(PORTAL_PATH)?
+        .build();
+    let mut signals = MessageStream::for_match_rule(
+        rule,
+        &connection,
+        None,
+    )
+    .await?;
+
+    // Nothing to listen to without a portal
+    read_portal_color_scheme_with(&connection)
+        .await
+        .ok_or(zbus::Error::Failure(
+            "no color scheme in the desktop portal"
+                .to_string(),
+        ))?;
+
+    while let Some(signal) = signals.next().await {
+        let Ok((namespace, key, value)) = signal?
+            .body()
+            .deserialize::<(String, String, OwnedValue)>()
+        else {
+            continue;
+        };
+        if namespace != APPEARANCE_NAMESPACE
+            || key != COLOR_SCHEME_KEY
+        {
+            continue;
+        }
+
+        let scheme = match scheme_from_portal_value(&value)
+        {
+            Some(scheme) => scheme,
+            // No preference any more
+            None => tokio::task::spawn_blocking(|| {
+                desktop_color_scheme().unwrap_or_default()
+            })
+            .await
+            .unwrap_or_default(),
+        };
+        let _ = output.send(scheme).await;
+    }
+
+    Ok(())
+}
+
+/// Settings of the desktop itself, for when the portal does
+/// not tell
+fn desktop_color_scheme() -> Option<ColorScheme> {
     match get_current_desktop_environment() {
         DesktopEnvironment::Gnome => {
-            let system_theme = Command::new("gsettings")
-                .args([
-                    "get",
-                    "org.gnome.desktop.interface",
-                    "color-scheme",
-                ])
-                .output()
-                .expect("Failed to get color scheme");
-
-            if let Ok(system_theme) =
-                String::from_utf8(system_theme.stdout)
+            gnome_color_scheme().or_else(gtk_color_scheme)
+        }
+        DesktopEnvironment::Kde => {
+            kde_color_scheme().or_else(gtk_color_scheme)
+        }
+        DesktopEnvironment::Unknown => {
+            gtk_color_scheme().or_else(kde_color_scheme)
+        }
+    }
+}
+
+/// Read `org.freedesktop.appearance color-scheme`, `None` when
+/// there is no portal or the user has no preference
+pub async fn read_portal_color_scheme(
+) -> Option<ColorScheme> {
+    let connection =
+        zbus::Connection::session().await.ok()?;
+    read_portal_color_scheme_with(&connection).await
+}
+
+async fn read_portal_color_scheme_with(
+    connection: &zbus::Connection,
+) -> Option<ColorScheme> {
+    let read = |method| {
+        connection.call_method(
+            Some(PORTAL_SERVICE),
+            PORTAL_PATH,
+            Some(SETTINGS_INTERFACE),
+            method,
+            &(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY),
+        )
+    };
+
+    let reply =
+        tokio::time::timeout(PORTAL_TIMEOUT, async {
+            // Older portals only have the deprecated `Read`
+            match read("ReadOne").await {
+                Ok(reply) => Ok(reply),
+                Err(_) => read("Read").await,
+            }
+        })
+        .await
+        .ok()?
+        .ok()?;
+
+    let value =
+        reply.body().deserialize::<OwnedValue>().ok()?;
+    scheme_from_portal_value(&value)
+}
+
+/// `1` is dark and `2` light, `Read` wrapping the number in
+/// one more variant
+fn scheme_from_portal_value(
+    value: &Value,
+) -> Option<ColorScheme> {
+    match value {
+        Value::U32(1) => Some(ColorScheme::Dark),
+        Value::U32(2) => Some(ColorScheme::Light),
+        Value::Value(inner) => {
+            scheme_from_portal_value(inner)
+        }
+        _ => None,
+    }
+}
+
+/// Query the portal from a thread of its own, so it works
+/// whether or not a runtime is already running
+fn portal_color_scheme() -> Option<ColorScheme> {
+    std::thread::spawn(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .ok()?
+            .block_on(read_portal_color_scheme())
+    })
+    .join()
+    .ok()
+    .flatten()
+}
+
+fn gnome_color_scheme() -> Option<ColorScheme> {
+    let output = Command::new("gsettings")
+        .args([
+            "get",
+            "org.gnome.desktop.interface",
+            "color-scheme",
+        ])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let scheme = String::from_utf8_lossy(&output.stdout);
+
+    if scheme.contains("dark") {
+        Some(ColorScheme::Dark)
+    } else if scheme.contains("light") {
+        Some(ColorScheme::Light)
+    } else {
+        // `default`, the theme itself may still be dark
+        None
+    }
+}
+
+/// Color scheme set in `kdeglobals`, going by its name or by
+/// how bright the window background is
+fn kde_color_scheme() -> Option<ColorScheme> {
+    let contents = fs::read_to_string(
+        config_dir()?.join("kdeglobals"),
+    )
+    .ok()?;
+
+    if let Some(name) =
+        ini_value(&contents, "General", "ColorScheme")
+    {
+        if name.to_lowercase().contains("dark") {
+            return Some(ColorScheme::Dark);
+        }
+    }
+
+    let background = ini_value(
+        &contents,
+        "Colors:Window",
+        "BackgroundNormal",
+    )?;
+    let channels = background
+        .split(',')
+        .map(|channel| channel.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    let [red, green, blue] = channels[..] else {
+        return None;
+    };
+    let luminance =
+        (0.2126 * red + 0.7152 * green + 0.0722 * blue)
+            / 255.0;
+
+    Some(if luminance < 0.5 {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    })
+}
+
+/// Dark preference of GTK, from `GTK_THEME` or the
+/// `settings.ini` of GTK 4 and 3
+fn gtk_color_scheme() -> Option<ColorScheme> {
+    if let Ok(theme) = env::var("GTK_THEME") {
+        return Some(scheme_from_theme_name(&theme));
+    }
+
+    let config = config_dir()?;
+    ["gtk-4.0", "gtk-3.0"].into_iter().find_map(|gtk| {
+        let contents = fs::read_to_string(
+            config.join(gtk).join("settings.ini"),
+        )
+        .ok()?;
+
+        let prefers_dark = ini_value(
+            &contents,
+            "Settings",
+            "gtk-application-prefer-dark-theme",
+        )
+        .map(|value| value == "1" || value == "true");
+        let theme_name = ini_value(
+            &contents,
+            "Settings",
+            "gtk-theme-name",
+        )
+        .map(|name| scheme_from_theme_name(&name));
+
+        match (prefers_dark, theme_name) {
+            (Some(true), _) => Some(ColorScheme::Dark),
+            (_, Some(scheme)) => Some(scheme),
+            (Some(false), None) => Some(ColorScheme::Light),
+            (None, None) => None,
+        }
+    })
+}
+
+fn scheme_from_theme_name(name: &str) -> ColorScheme {
+    if name.to_lowercase().contains("dark") {
+        ColorScheme::Dark
+    } else {
+        ColorScheme::Light
+    }
+}
+
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME").map(|home| {
+                PathBuf::from(home).join(".config")
+            })
+        })
+}
+
+/// Value of a key in a section of an INI style file
+fn ini_value(
+    contents: &str,
+    section: &str,
+    key: &str,
+) -> Option<String> {
+    let mut in_section = false;
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            in_section = name == section;
+        } else if in_section {
+            if let Some((name, value)) =
+                line.split_once('=')
             {
-                system_theme.contains("dark")
-            } else {
-                false
+                if name.trim() == key {
+                    return Some(value.trim().to_string());
+                }
             }
         }
-        DesktopEnvironment::Unknown => false,
     }
+
+    None
 }
 
 fn get_current_desktop_environment() -> DesktopEnvironment {
@@ -3,7 +3,8 @@ use iced::{
 };
 
 use super::{
-    colors_and_themes, get_palette_for_main_window,
+    button::get_focus_ring, colors_and_themes,
+    get_palette_for_main_window, theme::current_theme,
     CustomTheme,
 };
 
@@ -12,18 +13,23 @@ impl text_input::StyleSheet for CustomTheme {
 
     fn active(
         &self,
-        style: &Self::Style,
+        _style: &Self::Style,
     ) -> text_input::Appearance {
-        get_text_input_appearance(style.palette().primary)
+        let palette = get_palette_for_main_window();
+
+        get_text_input_appearance(palette.primary)
     }
 
     fn focused(
         &self,
         _style: &Self::Style,
     ) -> text_input::Appearance {
-        let palette = get_palette_for_main_window();
+        let focus_ring = get_focus_ring();
 
-        get_text_input_appearance(palette.primary)
+        text_input::Appearance {
+            border: focus_ring,
+            ..get_text_input_appearance(focus_ring.color)
+        }
     }
 
     fn placeholder_color(
@@ -35,8 +41,8 @@ impl text_input::StyleSheet for CustomTheme {
         Color::from_linear_rgba(r, g, b, a / 3.)
     }
 
-    fn value_color(&self, style: &Self::Style) -> Color {
-        style.palette().text
+    fn value_color(&self, _style: &Self::Style) -> Color {
+        current_theme().text
     }
 
     fn disabled_color(&self, style: &Self::Style) -> Color {
@@ -83,7 +89,9 @@ fn get_text_input_appearance(
         border: Border {
             color: border_color,
             width: 1.0,
-            radius: Radius::from(8.0),
+            radius: Radius::from(
+                current_theme().border_radius / 2.0,
+            ),
         },
         icon_color: border_color,
     }
@@ -0,0 +1,474 @@
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use iced::{theme::Palette, Color};
+
+use super::system::{color_scheme, ColorScheme};
+use crate::{config::ApplicationSettings, debug, t};
+
+/// Folder user themes are read from, one TOML or JSON file
+/// each
+pub const THEMES_DIR: &str = "themes";
+
+/// Theme picked in the settings, the built-in one matching
+/// the system color scheme being used when unset
+static SELECTED_THEME: RwLock<Option<UserTheme>> =
+    RwLock::new(None);
+/// Appearance settings applied over the theme
+static OVERRIDES: RwLock<Overrides> =
+    RwLock::new(Overrides {
+        opacity: None,
+        border_color: None,
+        border_radius: None,
+        high_contrast: false,
+    });
+/// Theme with the overrides applied, along with the color
+/// scheme it was resolved for, as styles ask for it on
+/// every redraw
+static CURRENT_THEME: RwLock<
+    Option<(ColorScheme, Arc<UserTheme>)>,
+> = RwLock::new(None);
+
+#[derive(Debug, Clone)]
+struct Overrides {
+    opacity: Option<f32>,
+    border_color: Option<Color>,
+    border_radius: Option<f32>,
+    high_contrast: bool,
+}
+
+/// Colors and shapes every style of the overlay is drawn
+/// with
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserTheme {
+    /// Name the settings refer to the theme by
+    pub name: String,
+    /// Catalog message naming a built-in theme in the
+    /// interface language
+    label: Option<&'static str>,
+    pub background: Color,
+    pub text: Color,
+    pub primary: Color,
+    /// Highlights, e.g. of search matches
+    pub accent: Color,
+    /// Border of the overlay
+    pub border_color: Color,
+    pub border_radius: f32,
+    /// Opacity of the background, from 0 to 1
+    pub opacity: f32,
+    /// Default text size, applied on the next start
+    pub font_size: f32,
+    pub success: Color,
+    pub danger: Color,
+}
+
+impl UserTheme {
+    /// Name shown in the theme picker
+    pub fn display_name(&self) -> String {
+        self.label
+            .map_or_else(|| self.name.clone(), |id| t!(id))
+    }
+
+    pub fn dark() -> Self {
+        Self::from_palette(
+            "Dark",
+            "theme-dark",
+            Color::BLACK,
+            iced::Theme::Oxocarbon.palette(),
+        )
+    }
+
+    pub fn light() -> Self {
+        Self::from_palette(
+            "Light",
+            "theme-light",
+            Color::WHITE,
+            iced::Theme::TokyoNightLight.palette(),
+        )
+    }
+
+    /// White on black with yellow highlights, for low
+    /// vision
+    pub fn high_contrast() -> Self {
+        let yellow = Color::from_rgb(1.0, 0.85, 0.0);
+
+        Self {
+            name: "High contrast".to_string(),
+            label: Some("theme-high-contrast"),
+            background: Color::BLACK,
+            text: Color::WHITE,
+            primary: yellow,
+            accent: yellow,
+            border_color: Color::WHITE,
+            success: Color::from_rgb(0.4, 1.0, 0.4),
+            danger: Color::from_rgb(1.0, 0.45, 0.45),
+            ..Self::dark()
+        }
+    }
+
+    fn from_palette(
+        name: &str,
+        label: &'static str,
+        background: Color,
+        palette: Palette,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            label: Some(label),
+            background,
+            text: palette.text,
+            primary: palette.primary,
+            accent: palette.primary,
+            border_color: Color::BLACK,
+            border_radius: 16.0,
+            opacity: 1.0,
+            font_size: 16.0,
+            success: palette.success,
+            danger: palette.danger,
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        Palette {
+            background: self.background,
+            text: self.text,
+            primary: self.primary,
+            success: self.success,
+            danger: self.danger,
+        }
+    }
+}
+
+/// Theme as written in a file, anything left out being
+/// taken from the built-in theme of the same brightness
+#[derive(serde::Deserialize, Debug, Default)]
+#[serde(default)]
+struct ThemeFile {
+    name: Option<String>,
+    background: Option<HexColor>,
+    text: Option<HexColor>,
+    primary: Option<HexColor>,
+    accent: Option<HexColor>,
+    border_color: Option<HexColor>,
+    border_radius: Option<f32>,
+    opacity: Option<f32>,
+    font_size: Option<f32>,
+}
+
+impl ThemeFile {
+    fn into_theme(self, default_name: String) -> UserTheme {
+        let is_dark = self
+            .background
+            .as_ref()
+            .map(|background| luminance(background.0) < 0.5)
+            .unwrap_or(color_scheme() == ColorScheme::Dark);
+        let base = if is_dark {
+            UserTheme::dark()
+        } else {
+            UserTheme::light()
+        };
+        let color = |color: Option<HexColor>, default| {
+            color.map(|color| color.0).unwrap_or(default)
+        };
+        let primary = color(self.primary, base.primary);
+
+        UserTheme {
+            name: self.name.unwrap_or(default_name),
+            label: None,
+            background: color(
+                self.background,
+                base.background,
+            ),
+            text: color(self.text, base.text),
+            primary,
+            accent: color(self.accent, primary),
+            border_color: color(
+                self.border_color,
+                base.border_color,
+            ),
+            border_radius: self
+                .border_radius
+                .unwrap_or(base.border_radius)
+                .max(0.0),
+            opacity: self
+                .opacity
+                .unwrap_or(base.opacity)
+                .clamp(0.0, 1.0),
+            font_size: self
+                .font_size
+                .unwrap_or(base.font_size)
+                .max(1.0),
+            ..base
+        }
+    }
+}
+
+/// Color written as `#rgb`, `#rrggbb` or `#rrggbbaa`
+#[derive(Debug)]
+struct HexColor(Color);
+
+impl<'de> serde::Deserialize<'de> for HexColor {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        parse_hex_color(&hex).map(HexColor).ok_or_else(
+            || {
+                serde::de::Error::custom(format!(
+                    "invalid color {hex}"
+                ))
+            },
+        )
+    }
+}
+
+pub fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.trim().strip_prefix('#')?;
+    // `from_str_radix` would take a leading `+`
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize, width: usize| {
+        let value = u8::from_str_radix(
+            &digits[i * width..(i + 1) * width],
+            16,
+        )
+        .ok()?;
+        // `f` stands for `ff`
+        Some(if width == 1 { value * 17 } else { value })
+    };
+
+    match digits.len() {
+        3 => Some(Color::from_rgb8(
+            channel(0, 1)?,
+            channel(1, 1)?,
+            channel(2, 1)?,
+        )),
+        6 | 8 => Some(Color::from_rgba8(
+            channel(0, 2)?,
+            channel(1, 2)?,
+            channel(2, 2)?,
+            if digits.len() == 8 {
+                f32::from(channel(3, 2)?) / 255.0
+            } else {
+                1.0
+            },
+        )),
+        _ => None,
+    }
+}
+
+fn luminance(color: Color) -> f32 {
+    0.2126 * color.r + 0.7152 * color.g + 0.0722 * color.b
+}
+
+/// Built-in themes followed by the ones of the themes
+/// folder, files that cannot be read being skipped
+pub fn load_themes() -> Vec<UserTheme> {
+    let mut themes =
+        vec![UserTheme::dark(), UserTheme::light()];
+
+    let Ok(entries) = std::fs::read_dir(THEMES_DIR) else {
+        return themes;
+    };
+    let mut paths = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        match load_theme(&path) {
+            Ok(Some(theme)) => {
+                themes.retain(|t| t.name != theme.name);
+                themes.push(theme);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                debug!(err);
+            }
+        }
+    }
+
+    themes
+}
+
+fn load_theme(
+    path: &Path,
+) -> Result<Option<UserTheme>, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str());
+    if !matches!(extension, Some("toml" | "json")) {
+        return Ok(None);
+    }
+
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| {
+            format!("{}: {err}", path.display())
+        })?;
+    let file = if extension == Some("toml") {
+        toml::from_str::<ThemeFile>(&contents)
+            .map_err(|err| err.to_string())
+    } else {
+        serde_json::from_str::<ThemeFile>(&contents)
+            .map_err(|err| err.to_string())
+    }
+    .map_err(|err| format!("{}: {err}", path.display()))?;
+
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(Some(file.into_theme(name)))
+}
+
+/// Use the theme of that name, or follow the system when
+/// there is none
+pub fn select_theme(
+    themes: &[UserTheme],
+    name: Option<&str>,
+) {
+    let theme = name.and_then(|name| {
+        themes.iter().find(|theme| theme.name == name)
+    });
+
+    if let Ok(mut selected) = SELECTED_THEME.write() {
+        *selected = theme.cloned();
+    }
+    clear_current_theme();
+}
+
+/// Take the appearance settings overriding the theme
+pub fn apply_appearance(settings: &ApplicationSettings) {
+    if let Ok(mut overrides) = OVERRIDES.write() {
+        *overrides = Overrides {
+            opacity: settings
+                .background_opacity
+                .map(|opacity| opacity.clamp(0.0, 1.0)),
+            border_color: settings
+                .border_color
+                .as_deref()
+                .and_then(parse_hex_color),
+            border_radius: settings
+                .corner_radius
+                .map(|radius| radius.max(0.0)),
+            high_contrast: settings.high_contrast,
+        };
+    }
+    clear_current_theme();
+}
+
+/// Theme to draw with right now
+pub fn current_theme() -> Arc<UserTheme> {
+    let scheme = color_scheme();
+    if let Some(theme) =
+        CURRENT_THEME.read().ok().and_then(|current| {
+            current
+                .as_ref()
+                .filter(|(resolved_for, _)| {
+                    *resolved_for == scheme
+                })
+                .map(|(_, theme)| theme.clone())
+        })
+    {
+        return theme;
+    }
+
+    let theme = Arc::new(resolve_theme());
+    if let Ok(mut current) = CURRENT_THEME.write() {
+        *current = Some((scheme, theme.clone()));
+    }
+    theme
+}
+
+fn clear_current_theme() {
+    if let Ok(mut current) = CURRENT_THEME.write() {
+        *current = None;
+    }
+}
+
+/// Selected theme with the appearance settings applied
+fn resolve_theme() -> UserTheme {
+    let mut theme = selected_theme();
+
+    if let Ok(overrides) = OVERRIDES.read() {
+        // Only the corners are kept, colors and opacity
+        // would lower the contrast
+        if overrides.high_contrast {
+            return UserTheme {
+                border_radius: overrides
+                    .border_radius
+                    .unwrap_or(theme.border_radius),
+                ..UserTheme::high_contrast()
+            };
+        }
+
+        theme.opacity =
+            overrides.opacity.unwrap_or(theme.opacity);
+        theme.border_color = overrides
+            .border_color
+            .unwrap_or(theme.border_color);
+        theme.border_radius = overrides
+            .border_radius
+            .unwrap_or(theme.border_radius);
+    }
+
+    theme
+}
+
+fn selected_theme() -> UserTheme {
+    if let Some(theme) = SELECTED_THEME
+        .read()
+        .ok()
+        .and_then(|selected| selected.clone())
+    {
+        return theme;
+    }
+
+    match color_scheme() {
+        ColorScheme::Dark => UserTheme::dark(),
+        ColorScheme::Light => UserTheme::light(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_short_long_and_alpha_colors() {
+        assert_eq!(
+            parse_hex_color("#fff"),
+            Some(Color::WHITE)
+        );
+        assert_eq!(
+            parse_hex_color(" #000000 "),
+            Some(Color::BLACK)
+        );
+        assert_eq!(
+            parse_hex_color("#FF8000"),
+            Some(Color::from_rgb8(255, 128, 0))
+        );
+        assert_eq!(
+            parse_hex_color("#00000080"),
+            Some(Color::from_rgba8(0, 0, 0, 128.0 / 255.0))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for color in [
+            "", "#", "fff", "#ff", "#ffff", "#fffffff",
+            "#ggg", "#12345z", "#ééé", "#+1+2+3",
+        ] {
+            assert_eq!(
+                parse_hex_color(color),
+                None,
+                "{color}"
+            );
+        }
+    }
+}
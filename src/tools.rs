@@ -0,0 +1,528 @@
+use std::{fs, path::PathBuf, time::Duration};
+
+use serde_json::{json, Value};
+
+use crate::{api::ToolCall, config::ApplicationSettings};
+
+/// Characters of a tool output given back to the model
+const MAX_OUTPUT_LENGTH: usize = 8000;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+/// Parentheses and signs a calculation may nest, deeper
+/// ones being refused before they overflow the stack
+const MAX_NESTING: usize = 100;
+
+/// Definitions of the built-in tools, as expected in the
+/// `tools` field of a chat request
+pub fn definitions() -> Vec<Value> {
+    [
+        (
+            "read_file",
+            "Read a text file of the user's workspace",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path relative to the workspace"
+                    }
+                },
+                "required": ["path"]
+            }),
+        ),
+        (
+            "list_directory",
+            "List the entries of a directory of the user's workspace",
+            json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path relative to the workspace, `.` for its root"
+                    }
+                },
+                "required": ["path"]
+            }),
+        ),
+        (
+            "current_datetime",
+            "Current local date, time and time zone",
+            json!({ "type": "object", "properties": {} }),
+        ),
+        (
+            "calculator",
+            "Evaluate an arithmetic expression made of numbers, \
+             + - * / % ^ and parentheses",
+            json!({
+                "type": "object",
+                "properties": {
+                    "expression": { "type": "string" }
+                },
+                "required": ["expression"]
+            }),
+        ),
+        (
+            "run_command",
+            "Run a command of the user's allowlist, exactly as \
+             listed, in the workspace, without a shell",
+            json!({
+                "type": "object",
+                "properties": {
+                    "command": {
+                        "type": "string",
+                        "description": "Program and arguments, separated by spaces"
+                    }
+                },
+                "required": ["command"]
+            }),
+        ),
+    ]
+    .into_iter()
+    .map(|(name, description, parameters)| {
+        json!({
+            "type": "function",
+            "function": {
+                "name": name,
+                "description": description,
+                "parameters": parameters
+            }
+        })
+    })
+    .collect()
+}
+
+/// One line summary of a call, shown when asking the user to
+/// approve it
+pub fn describe(call: &ToolCall) -> String {
+    let arguments = match &call.function.arguments {
+        Value::Object(arguments) => arguments
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+
+    format!("{}({arguments})", call.function.name)
+}
+
+/// Run a tool call, failures being reported to the model as
+/// the tool output
+pub async fn run_tool(
+    call: &ToolCall,
+    settings: &ApplicationSettings,
+) -> String {
+    let argument = |name: &str| {
+        call.function
+            .arguments
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or(format!("missing `{name}` argument"))
+    };
+
+    let result = match call.function.name.as_str() {
+        "read_file" => argument("path")
+            .and_then(|path| read_file(path, settings)),
+        "list_directory" => {
+            argument("path").and_then(|path| {
+                list_directory(path, settings)
+            })
+        }
+        "current_datetime" => Ok(chrono::Local::now()
+            .format("%A %Y-%m-%d %H:%M:%S %Z")
+            .to_string()),
+        "calculator" => {
+            argument("expression").and_then(calculate)
+        }
+        "run_command" => match argument("command") {
+            Ok(command) => {
+                run_command(command, settings).await
+            }
+            Err(err) => Err(err),
+        },
+        other => Err(format!("unknown tool `{other}`")),
+    };
+
+    let output = result
+        .unwrap_or_else(|err| format!("Error: {err}"));
+    if output.chars().count() > MAX_OUTPUT_LENGTH {
+        let truncated = output
+            .chars()
+            .take(MAX_OUTPUT_LENGTH)
+            .collect::<String>();
+        format!("{truncated}\n[output truncated]")
+    } else {
+        output
+    }
+}
+
+fn workspace(
+    settings: &ApplicationSettings,
+) -> Result<PathBuf, String> {
+    let directory = settings
+        .workspace_dir
+        .as_ref()
+        .filter(|dir| !dir.trim().is_empty())
+        .ok_or("no workspace folder configured")?;
+
+    fs::canonicalize(directory.trim()).map_err(|err| {
+        format!("cannot open workspace {directory}: {err}")
+    })
+}
+
+/// Resolve a path given by the model, refusing anything that
+/// ends up outside the workspace, symbolic links included
+fn resolve(
+    path: &str,
+    settings: &ApplicationSettings,
+) -> Result<PathBuf, String> {
+    let workspace = workspace(settings)?;
+    let resolved = fs::canonicalize(
+        workspace.join(path.trim_start_matches('/')),
+    )
+    .map_err(|err| format!("cannot open {path}: {err}"))?;
+
+    if resolved.starts_with(&workspace) {
+        Ok(resolved)
+    } else {
+        Err(format!("{path} is outside the workspace"))
+    }
+}
+
+fn read_file(
+    path: &str,
+    settings: &ApplicationSettings,
+) -> Result<String, String> {
+    fs::read_to_string(resolve(path, settings)?)
+        .map_err(|err| format!("cannot read {path}: {err}"))
+}
+
+fn list_directory(
+    path: &str,
+    settings: &ApplicationSettings,
+) -> Result<String, String> {
+    let entries = fs::read_dir(resolve(path, settings)?)
+        .map_err(|err| {
+            format!("cannot list {path}: {err}")
+        })?;
+
+    let mut names = entries
+        .flatten()
+        .map(|entry| {
+            let name = entry
+                .file_name()
+                .to_string_lossy()
+                .to_string();
+            if entry.path().is_dir() {
+                format!("{name}/")
+            } else {
+                name
+            }
+        })
+        .collect::<Vec<_>>();
+    names.sort();
+
+    Ok(names.join("\n"))
+}
+
+/// Whether a command is one of the allowed ones, word for
+/// word. Extra arguments are refused, as they could make an
+/// allowed program write anywhere, e.g.
+/// `git log --output=<path>`
+fn is_allowed(
+    command: &[&str],
+    allowed: &[String],
+) -> bool {
+    !command.is_empty()
+        && allowed.iter().any(|allowed| {
+            allowed
+                .split_whitespace()
+                .eq(command.iter().copied())
+        })
+}
+
+/// Run one of the allowed commands in the workspace
+async fn run_command(
+    command: &str,
+    settings: &ApplicationSettings,
+) -> Result<String, String> {
+    let words =
+        command.split_whitespace().collect::<Vec<_>>();
+    if !is_allowed(&words, &settings.allowed_commands) {
+        return Err(format!(
+            "`{command}` is not in the allowed commands"
+        ));
+    }
+
+    let directory = workspace(settings)?;
+    let child = tokio::process::Command::new(words[0])
+        .args(&words[1..])
+        .current_dir(directory)
+        .kill_on_drop(true)
+        .output();
+
+    let output =
+        tokio::time::timeout(COMMAND_TIMEOUT, child)
+            .await
+            .map_err(|_| {
+                format!(
+                    "timed out after {}s",
+                    COMMAND_TIMEOUT.as_secs()
+                )
+            })?
+            .map_err(|err| err.to_string())?;
+
+    Ok(format!(
+        "Exit status: {}\n{}{}",
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))
+}
+
+fn calculate(expression: &str) -> Result<String, String> {
+    let mut parser = Calculator {
+        chars: expression
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect(),
+        position: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+
+    if parser.position < parser.chars.len() {
+        Err(format!(
+            "unexpected `{}`",
+            parser.chars[parser.position]
+        ))
+    } else if !value.is_finite() {
+        Err("result is not a finite number".to_string())
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Recursive descent parser evaluating as it reads
+struct Calculator {
+    chars: Vec<char>,
+    position: usize,
+    /// Nesting level of the part being read
+    depth: usize,
+}
+
+impl Calculator {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matches = self.peek() == Some(c);
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    /// Read a part nested in the current one, every
+    /// recursion going through here
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<f64, String>,
+    ) -> Result<f64, String> {
+        if self.depth == MAX_NESTING {
+            return Err(
+                "expression nested too deeply".to_string()
+            );
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn expression(&mut self) -> Result<f64, String> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+        loop {
+            if self.eat('*') {
+                value *= self.power()?;
+            } else if self.eat('/') {
+                value /= self.power()?;
+            } else if self.eat('%') {
+                value %= self.power()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Right associative, a leading minus applying to the
+    /// whole power as in `-2^2 = -4`
+    fn power(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.nested(Self::power)?);
+        }
+        if self.eat('+') {
+            return self.nested(Self::power);
+        }
+
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(base.powf(self.nested(Self::power)?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> Result<f64, String> {
+        if self.eat('(') {
+            let value = self.nested(Self::expression)?;
+            return if self.eat(')') {
+                Ok(value)
+            } else {
+                Err("missing `)`".to_string())
+            };
+        }
+
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.')
+        {
+            self.position += 1;
+        }
+
+        let number = self.chars[start..self.position]
+            .iter()
+            .collect::<String>();
+        match self.peek() {
+            _ if !number.is_empty() => {
+                number.parse().map_err(|_| {
+                    format!("invalid number {number}")
+                })
+            }
+            Some(c) => Err(format!("unexpected `{c}`")),
+            None => {
+                Err("unexpected end of expression"
+                    .to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculator_follows_precedence() {
+        for (expression, expected) in [
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("2 ^ 3 ^ 2", "512"),
+            ("-2 ^ 2", "-4"),
+            ("10 % 4 - -1", "3"),
+            ("7 / 2", "3.5"),
+            ("+.5", "0.5"),
+        ] {
+            assert_eq!(
+                calculate(expression).as_deref(),
+                Ok(expected),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn calculator_rejects_invalid_expressions() {
+        for expression in [
+            "", "1 +", "(1 + 2", "1 )", "2 * x", "1.2.3",
+            "1 / 0",
+        ] {
+            assert!(
+                calculate(expression).is_err(),
+                "{expression}"
+            );
+        }
+    }
+
+    #[test]
+    fn calculator_limits_nesting() {
+        let nested = |depth: usize| {
+            format!(
+                "{}1{}",
+                "(".repeat(depth),
+                ")".repeat(depth)
+            )
+        };
+
+        assert_eq!(
+            calculate(&nested(50)).as_deref(),
+            Ok("1")
+        );
+        for expression in [
+            nested(100_000),
+            "-".repeat(100_000) + "1",
+            "2^".repeat(100_000) + "1",
+        ] {
+            assert_eq!(
+                calculate(&expression),
+                Err("expression nested too deeply"
+                    .to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn commands_must_match_the_allowlist_exactly() {
+        let allowed = [
+            "git log".to_string(),
+            "  uname   -a ".to_string(),
+        ];
+        let allows = |command: &str| {
+            is_allowed(
+                &command
+                    .split_whitespace()
+                    .collect::<Vec<_>>(),
+                &allowed,
+            )
+        };
+
+        assert!(allows("git log"));
+        assert!(allows("uname -a"));
+        assert!(!allows("git log --output=/etc/passwd"));
+        assert!(!allows("git"));
+        assert!(!allows("git status"));
+        assert!(!allows("uname"));
+        assert!(!allows(""));
+        assert!(!is_allowed(&[], &["".to_string()]));
+    }
+
+    #[test]
+    fn commands_need_a_workspace() {
+        let settings = ApplicationSettings {
+            allowed_commands: vec!["true".to_string()],
+            workspace_dir: None,
+            ..ApplicationSettings::default()
+        };
+
+        assert!(iced::futures::executor::block_on(
+            run_command("true", &settings)
+        )
+        .is_err());
+    }
+}
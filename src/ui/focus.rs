@@ -0,0 +1,279 @@
+use iced::{
+    advanced::{
+        layout, mouse, overlay, renderer,
+        widget::{operation, tree, Operation, Tree},
+        Clipboard, Layout, Shell, Widget,
+    },
+    event::{self, Event},
+    keyboard::{self, key::Named},
+    Color, Element, Length, Rectangle, Renderer, Size,
+    Theme, Vector,
+};
+
+use crate::styles::button::get_focus_ring;
+
+/// Widget reachable with Tab and Shift+Tab, drawn with a
+/// ring while focused. Enter and Space press it, the arrow
+/// keys step through its values and Escape unfocuses it
+pub struct Focusable<'a, Message> {
+    content: Element<'a, Message>,
+    on_press: Option<Message>,
+    on_previous: Option<Message>,
+    on_next: Option<Message>,
+}
+
+pub fn focusable<'a, Message>(
+    content: impl Into<Element<'a, Message>>,
+) -> Focusable<'a, Message> {
+    Focusable {
+        content: content.into(),
+        on_press: None,
+        on_previous: None,
+        on_next: None,
+    }
+}
+
+impl<'a, Message> Focusable<'a, Message> {
+    pub fn on_press(mut self, message: Message) -> Self {
+        self.on_press = Some(message);
+        self
+    }
+
+    pub fn on_press_maybe(
+        mut self,
+        message: Option<Message>,
+    ) -> Self {
+        self.on_press = message;
+        self
+    }
+
+    /// Messages of the Up or Left and Down or Right keys
+    pub fn on_step(
+        mut self,
+        previous: Message,
+        next: Message,
+    ) -> Self {
+        self.on_previous = Some(previous);
+        self.on_next = Some(next);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    is_focused: bool,
+}
+
+impl operation::Focusable for State {
+    fn is_focused(&self) -> bool {
+        self.is_focused
+    }
+
+    fn focus(&mut self) {
+        self.is_focused = true;
+    }
+
+    fn unfocus(&mut self) {
+        self.is_focused = false;
+    }
+}
+
+impl<'a, Message: Clone> Widget<Message, Theme, Renderer>
+    for Focusable<'a, Message>
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(
+            &self.content,
+        ));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content.as_widget().layout(
+            &mut tree.children[0],
+            renderer,
+            limits,
+        )
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation<Message>,
+    ) {
+        operation.focusable(
+            tree.state.downcast_mut::<State>(),
+            None,
+        );
+        self.content.as_widget().operate(
+            &mut tree.children[0],
+            layout,
+            renderer,
+            operation,
+        );
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+
+        match &event {
+            // The mouse takes the focus back, like clicking
+            // out of a text input
+            Event::Mouse(mouse::Event::ButtonPressed(
+                _,
+            )) => {
+                state.is_focused = false;
+            }
+            Event::Keyboard(
+                keyboard::Event::KeyPressed {
+                    key: keyboard::Key::Named(key),
+                    ..
+                },
+            ) if state.is_focused => {
+                // Like in a text input, Escape only gives the
+                // focus up, without closing the overlay
+                if *key == Named::Escape {
+                    state.is_focused = false;
+                    return event::Status::Captured;
+                }
+
+                let message = match key {
+                    Named::Enter | Named::Space => {
+                        self.on_press.as_ref()
+                    }
+                    Named::ArrowUp | Named::ArrowLeft => {
+                        self.on_previous.as_ref()
+                    }
+                    Named::ArrowDown
+                    | Named::ArrowRight => {
+                        self.on_next.as_ref()
+                    }
+                    _ => None,
+                };
+                if let Some(message) = message {
+                    shell.publish(message.clone());
+                    return event::Status::Captured;
+                }
+            }
+            _ => {}
+        }
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> mouse::Interaction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            style,
+            layout,
+            cursor,
+            viewport,
+        );
+
+        if tree.state.downcast_ref::<State>().is_focused {
+            <Renderer as renderer::Renderer>::fill_quad(
+                renderer,
+                renderer::Quad {
+                    bounds: layout.bounds(),
+                    border: get_focus_ring(),
+                    ..renderer::Quad::default()
+                },
+                Color::TRANSPARENT,
+            );
+        }
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<
+        overlay::Element<'b, Message, Theme, Renderer>,
+    > {
+        self.content.as_widget_mut().overlay(
+            &mut tree.children[0],
+            layout,
+            renderer,
+            translation,
+        )
+    }
+}
+
+impl<'a, Message: Clone + 'a> From<Focusable<'a, Message>>
+    for Element<'a, Message>
+{
+    fn from(focusable: Focusable<'a, Message>) -> Self {
+        Element::new(focusable)
+    }
+}
@@ -1,69 +1,153 @@
+use std::{
+    collections::HashSet,
+    ops::{Range, RangeInclusive},
+};
+
 use iced::{
     alignment::{Horizontal, Vertical},
     widget::{
-        button, checkbox, combo_box, container,
-        horizontal_rule, svg, text, text_input,
-        vertical_space, Column, Row, Scrollable,
+        button, checkbox, container, horizontal_rule,
+        mouse_area, pick_list, progress_bar,
+        scrollable::{Direction, Properties},
+        slider, svg, text, text_input, tooltip,
+        vertical_space, Column, Row, Scrollable, Space,
     },
-    Element, Length,
+    Alignment, Color, Element, Length,
 };
+use serde_json::Value;
 
 use crate::{
+    ai::{
+        ModelStatus, RetryState, ToolRequest, MIN_NUM_CTX,
+    },
+    chat::{Conversation, Role},
+    compare::Comparison,
+    config::ApplicationSettings,
+    conversations::{ConversationStore, SavedConversation},
+    export::ExportFormat,
+    fonts,
+    geometry::WindowPlacement,
+    health::HealthReport,
+    i18n::Language,
+    pull::PullState,
+    rag::DocumentsStatus,
+    search::{format_date, SearchHit},
     styles::{
         button::get_btn_transparent_style,
-        text_input::get_text_input_style, PADDING_SIZE,
+        container::get_status_dot_style,
+        text_input::get_text_input_style,
+        theme::{
+            current_theme, parse_hex_color, UserTheme,
+            THEMES_DIR,
+        },
+        PADDING_SIZE,
     },
+    t, tools,
+    usage::UsageStore,
     AppState, MainMessage,
 };
 
-use super::RouterView;
+use super::{focus::focusable, RouterView};
 
 pub fn top_bar<'a>(
     icon: svg::Handle,
     view_on_click: RouterView,
-    api_is_live: bool,
+    health: &Option<HealthReport>,
 ) -> impl Into<Element<'a, MainMessage>> {
+    let (label, color, details) = match health {
+        None => (
+            t!("health-checking"),
+            Color::from_rgb(0.6, 0.6, 0.6),
+            t!("health-checking-details"),
+        ),
+        Some(report) if report.is_live() => (
+            t!("health-online"),
+            Color::from_rgb(0.2, 0.7, 0.3),
+            report.describe(),
+        ),
+        Some(report) => (
+            t!("health-offline"),
+            Color::from_rgb(0.85, 0.2, 0.2),
+            report.describe(),
+        ),
+    };
+
+    // Clicking the indicator checks again right away
+    let indicator = button(
+        Row::new()
+            .push(
+                container(Space::new(10, 10))
+                    .style(get_status_dot_style(color)),
+            )
+            .push(text(label).size(14))
+            .spacing(6)
+            .align_items(Alignment::Center),
+    )
+    .on_press(MainMessage::RunAiHealthCheck)
+    .style(get_btn_transparent_style());
+
+    // Empty space of the bar moves the window, which has no
+    // decorations
+    let handle = mouse_area(
+        Row::new()
+            .push(
+                text(t!("app-title"))
+                    .size(20)
+                    .width(Length::Shrink)
+                    .vertical_alignment(Vertical::Center)
+                    .horizontal_alignment(Horizontal::Left),
+            )
+            .push(
+                container(
+                    focusable(
+                        tooltip(
+                            indicator,
+                            text(details).size(12),
+                            tooltip::Position::Bottom,
+                        )
+                        .style(iced::theme::Container::Box),
+                    )
+                    .on_press(
+                        MainMessage::RunAiHealthCheck,
+                    ),
+                )
+                .width(Length::Fill),
+            ),
+    )
+    .on_press(MainMessage::DragWindow);
+
     Row::new()
         .push(
-            text("AI Overlay")
-                .size(20)
-                .width(Length::Shrink)
-                .vertical_alignment(Vertical::Center)
-                .horizontal_alignment(Horizontal::Left),
+            container(handle).width(Length::FillPortion(1)),
         )
         .push(
-            container(
-                checkbox(
-                    if api_is_live {
-                        "API Works"
-                    } else {
-                        "API is OFF"
-                    },
-                    api_is_live,
-                )
-                .on_toggle_maybe(if api_is_live {
-                    None
-                } else {
-                    Some(|_| MainMessage::RunAiHealthCheck)
-                })
-                .spacing(4)
-                .size(16),
-            )
-            .padding(4)
-            .width(Length::FillPortion(1)),
-        )
-        .push(
-            Row::new().push(
-                button(
+            Row::new()
+                .push(action_button(
+                    t!("nav-chats"),
+                    MainMessage::ToggleSidebar,
+                ))
+                .push(action_button(
+                    t!("nav-search"),
+                    MainMessage::ChangeView(
+                        RouterView::Search,
+                    ),
+                ))
+                .push(action_button(
+                    t!("nav-compare"),
+                    MainMessage::ChangeView(
+                        RouterView::Compare,
+                    ),
+                ))
+                .push(icon_button(
                     svg(icon)
                         .width(Length::Fixed(18.))
                         .height(Length::Fixed(18.)),
-                )
-                .on_press(MainMessage::ChangeView(
-                    view_on_click,
-                ))
-                .style(get_btn_transparent_style()),
-            ),
+                    match view_on_click {
+                        RouterView::Home => t!("nav-back"),
+                        _ => t!("nav-settings"),
+                    },
+                    MainMessage::ChangeView(view_on_click),
+                )),
         )
         .padding([0, 0, PADDING_SIZE, 0])
 }
@@ -72,7 +156,7 @@ pub fn search_bar<'a>(
     text: &str,
 ) -> impl Into<Element<'a, MainMessage>> {
     container(
-        text_input("AI Message", text)
+        text_input(&t!("prompt-placeholder"), text)
             .padding(PADDING_SIZE)
             .size(20)
             .style(get_text_input_style())
@@ -83,79 +167,1563 @@ pub fn search_bar<'a>(
     .center_x()
 }
 
+/// Prompt field, with the share of the context window the
+/// conversation takes, unknown while it is being summarized
+pub fn prompt_bar<'a>(
+    user_input: &str,
+    is_editing_prompt: bool,
+    context_tokens: Option<usize>,
+    context_length: u32,
+) -> Element<'a, MainMessage> {
+    let context_usage = text(match context_tokens {
+        Some(used) => t!(
+            "context-usage",
+            used = used,
+            total = context_length
+        ),
+        None => t!("context-summarizing"),
+    })
+    .size(12);
+
+    Column::new()
+        .push_maybe(is_editing_prompt.then(edit_prompt_bar))
+        .push(search_bar(user_input).into())
+        .push(
+            container(context_usage)
+                .width(Length::Fill)
+                .align_x(Horizontal::Right),
+        )
+        .into()
+}
+
+/// Home page: the prompt, then whatever the last request
+/// came back with
 pub fn main_page_content<'a>(
     app_state: &AppState,
-    user_input: &str,
-    ai_response: &str,
+    prompt: Element<'a, MainMessage>,
+    retry: &Option<RetryState>,
+    tool_request: &Option<ToolRequest>,
+    missing_model: Option<Element<'a, MainMessage>>,
     error: &Option<String>,
+    reply: Option<Element<'a, MainMessage>>,
 ) -> impl Into<Element<'a, MainMessage>> {
-    let ai_input = search_bar(user_input).into();
-
-    match (app_state, ai_response, error) {
-        (AppState::Done, response, None)
-            if !response.is_empty() =>
-        {
-            let scroll = Scrollable::new(
-                Column::new()
-                    .push(vertical_space().height(4))
-                    .push(
-                        container(text(response))
-                            .height(Length::Fill)
-                            .width(Length::Fill)
-                            .padding([
-                                0,
-                                PADDING_SIZE,
-                                0,
-                                PADDING_SIZE,
-                            ]),
-                    )
-                    .height(155),
-            );
+    if let AppState::Loading = app_state {
+        return Column::new().push(loading_status(retry));
+    }
+    if let Some(request) = tool_request {
+        return Column::new()
+            .push(prompt)
+            .push(tool_confirmation(request));
+    }
+    if let Some(missing_model) = missing_model {
+        return Column::new()
+            .push(prompt)
+            .push(missing_model);
+    }
 
-            Column::new()
-                .push(ai_input)
-                .push(
-                    container(text("AI's response : "))
-                        .padding([
-                            PADDING_SIZE,
-                            0,
-                            PADDING_SIZE,
-                            PADDING_SIZE,
-                        ]),
-                )
-                .push(horizontal_rule(1))
-                .push(scroll)
-        }
-        (AppState::Done, _, Some(err_msg)) => Column::new()
-            .push(ai_input)
+    match (error, reply) {
+        (Some(err_msg), _) => Column::new()
+            .push(prompt)
             .push(vertical_space().height(4))
             .push(
-                container(text("There was an error :("))
+                container(text(t!("error-title")))
                     .center_x(),
             )
             .push(vertical_space().height(4))
             .push(text(err_msg)),
-        (AppState::Done, _, None) => {
-            Column::new().push(ai_input)
+        (None, reply) => {
+            Column::new().push(prompt).push_maybe(reply)
         }
-        (AppState::Loading, _, _) => Column::new().push(
-            container(text("In progress ..."))
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .center_x()
-                .center_y(),
+    }
+}
+
+fn loading_status<'a>(
+    retry: &Option<RetryState>,
+) -> Element<'a, MainMessage> {
+    container(
+        Column::new()
+            .push(text(t!("in-progress")))
+            .push_maybe(retry.as_ref().map(|retry| {
+                text(t!(
+                    "retry-status",
+                    attempt = retry.attempt,
+                    max = retry.max_attempts,
+                    seconds = retry.delay.as_secs(),
+                    error = retry.error.clone()
+                ))
+                .size(12)
+            }))
+            .align_items(Alignment::Center)
+            .spacing(4),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .center_x()
+    .center_y()
+    .into()
+}
+
+/// Last reply of the conversation with its sources, stats
+/// and the buttons acting on it
+pub fn reply_content<'a>(
+    conversation: &Conversation,
+    reply: usize,
+    collapsed_json_nodes: &HashSet<String>,
+    settings: &ApplicationSettings,
+    reply_height: f32,
+    is_expanded: bool,
+) -> Element<'a, MainMessage> {
+    let message = conversation.message(reply);
+
+    let citations = &message.citations;
+    let sources = (!citations.is_empty()).then(|| {
+        container(
+            text(t!(
+                "reply-sources",
+                sources = citations
+                    .iter()
+                    .enumerate()
+                    .map(|(i, citation)| {
+                        format!("[{}] {citation}", i + 1)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+            .size(12),
+        )
+        .padding([4, PADDING_SIZE])
+    });
+    let tools_used = &message.tools_used;
+    let tools_used = (!tools_used.is_empty()).then(|| {
+        container(
+            text(t!(
+                "reply-tools",
+                tools = tools_used.join(", ")
+            ))
+            .size(12),
+        )
+        .padding([4, PADDING_SIZE])
+    });
+
+    let tree = message
+        .structured
+        .then(|| {
+            serde_json::from_str::<Value>(&message.content)
+                .ok()
+        })
+        .flatten();
+    let body: Element<'a, MainMessage> = match tree {
+        Some(value) => json_tree(
+            String::new(),
+            &value,
+            String::new(),
+            collapsed_json_nodes,
         ),
+        None => reply_text(&message.content, settings),
+    };
+
+    let scroll = Scrollable::new(
+        Column::new()
+            .push(vertical_space().height(4))
+            .push(
+                container(body)
+                    .height(Length::Fill)
+                    .width(Length::Fill)
+                    .padding([
+                        0,
+                        PADDING_SIZE,
+                        0,
+                        PADDING_SIZE,
+                    ]),
+            )
+            .push_maybe(sources)
+            .push_maybe(tools_used)
+            .height(reply_height),
+    );
+    let stats = message.stats.map(|stats| {
+        container(text(stats.to_string()).size(12))
+            .width(Length::Fill)
+            .align_x(Horizontal::Right)
+    });
+
+    let actions = Row::new()
+        .push(
+            container(text(t!("reply-title") + " "))
+                .padding([
+                    PADDING_SIZE,
+                    0,
+                    PADDING_SIZE,
+                    PADDING_SIZE,
+                ])
+                .width(Length::Fill),
+        )
+        .push_maybe(conversation.last_prompt().map(
+            |prompt| {
+                branch_switcher(
+                    conversation,
+                    prompt,
+                    &t!("branch-prompt"),
+                )
+            },
+        ))
+        .push(branch_switcher(
+            conversation,
+            reply,
+            &t!("branch-reply"),
+        ))
+        .push(action_button(
+            t!("edit-prompt"),
+            MainMessage::EditLastPrompt,
+        ))
+        .push(action_button(
+            if is_expanded {
+                t!("collapse")
+            } else {
+                t!("expand")
+            },
+            MainMessage::ToggleExpanded,
+        ))
+        .push(action_button(
+            t!("regenerate"),
+            MainMessage::RegenerateReply,
+        ))
+        .align_items(Alignment::Center);
+
+    Column::new()
+        .push(actions)
+        .push(horizontal_rule(1))
+        .push(scroll)
+        .push_maybe(stats)
+        .into()
+}
+
+/// Reply in the reply font, its fenced code blocks being
+/// set in the code font
+fn reply_text<'a>(
+    content: &str,
+    settings: &ApplicationSettings,
+) -> Element<'a, MainMessage> {
+    let reply_font = fonts::reply_font(settings);
+    let code_font = fonts::code_font(settings);
+
+    content
+        .split("```")
+        .enumerate()
+        .fold(
+            Column::new().spacing(4),
+            |column, (i, block)| {
+                if i % 2 == 1 {
+                    // Skip the language of the block
+                    let code = block
+                        .split_once('\n')
+                        .map_or(block, |(_, code)| code)
+                        .trim_end()
+                        .replace('\t', "    ");
+                    column.push(
+                        container(
+                            text(code).font(code_font),
+                        )
+                        .padding([0, PADDING_SIZE]),
+                    )
+                } else {
+                    let prose = block
+                        .trim_matches('\n')
+                        .replace('\t', " ")
+                        .replace("\n\n", "\n");
+                    column.push_maybe(
+                        (!prose.is_empty()).then(|| {
+                            text(prose).font(reply_font)
+                        }),
+                    )
+                }
+            },
+        )
+        .into()
+}
+
+/// Collapsible view of a JSON reply, each node being
+/// identified by its JSON pointer
+fn json_tree<'a>(
+    label: String,
+    value: &Value,
+    pointer: String,
+    collapsed: &HashSet<String>,
+) -> Element<'a, MainMessage> {
+    let (children, open, close) = match value {
+        Value::Object(object) => (
+            object
+                .iter()
+                .map(|(key, child)| (key.clone(), child))
+                .collect::<Vec<_>>(),
+            "{",
+            "}",
+        ),
+        Value::Array(items) => (
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (i.to_string(), child))
+                .collect(),
+            "[",
+            "]",
+        ),
+        leaf => {
+            return text(format!("{label}{leaf}"))
+                .size(14)
+                .into()
+        }
+    };
+
+    let is_collapsed = collapsed.contains(&pointer);
+    let header = button(
+        text(if is_collapsed {
+            format!(
+                "[+] {label}{open} {} {close}",
+                children.len()
+            )
+        } else {
+            format!("[-] {label}{open}")
+        })
+        .size(14),
+    )
+    .on_press(MainMessage::ToggleJsonNode(pointer.clone()))
+    .padding(0)
+    .style(get_btn_transparent_style());
+    let header = focusable(header).on_press(
+        MainMessage::ToggleJsonNode(pointer.clone()),
+    );
+
+    if is_collapsed {
+        return header.into();
     }
+
+    let children = children.into_iter().fold(
+        Column::new(),
+        |column, (key, child)| {
+            let escaped =
+                key.replace('~', "~0").replace('/', "~1");
+            column.push(json_tree(
+                format!("{key}: "),
+                child,
+                format!("{pointer}/{escaped}"),
+                collapsed,
+            ))
+        },
+    );
+
+    Column::new()
+        .push(header)
+        .push(container(children).padding([0, 0, 0, 16]))
+        .push(text(close).size(14))
+        .into()
+}
+
+/// Offer to download a model that is not installed, or to
+/// switch to one that is, along with why the last download
+/// failed
+pub fn missing_model_prompt<'a>(
+    model: &str,
+    installed_models: &[String],
+    pull: &Option<PullState>,
+    error: &Option<String>,
+) -> Element<'a, MainMessage> {
+    let download: Element<'a, MainMessage> = match pull {
+        Some(pull) => Column::new()
+            .push(
+                text(t!(
+                    "model-pulling",
+                    model = pull.model.clone(),
+                    status = pull.status.clone()
+                ))
+                .size(14),
+            )
+            .push(
+                progress_bar(0.0..=100.0, pull.percent)
+                    .height(8),
+            )
+            .spacing(4)
+            .into(),
+        None => action_button(
+            t!("model-pull", model = model.to_string()),
+            MainMessage::PullModel(model.to_string()),
+        ),
+    };
+
+    let models = installed_models.iter().fold(
+        Row::new()
+            .push(text(t!("model-use-other")).size(14)),
+        |row, installed| {
+            row.push(action_button(
+                installed.clone(),
+                MainMessage::UpdateConfigModel(
+                    installed.clone(),
+                ),
+            ))
+        },
+    );
+
+    Column::new()
+        .push(vertical_space().height(4))
+        .push(text(t!(
+            "model-missing",
+            model = model.to_string()
+        )))
+        .push(download)
+        .push_maybe(
+            error
+                .as_ref()
+                .map(|error| text(error).size(14)),
+        )
+        .push_maybe((!installed_models.is_empty()).then(
+            || {
+                Scrollable::new(
+                    models.align_items(Alignment::Center),
+                )
+                .direction(
+                    Direction::Horizontal(
+                        Properties::default(),
+                    ),
+                )
+            },
+        ))
+        .spacing(4)
+        .padding([0, PADDING_SIZE])
+        .into()
 }
 
+/// Tool calls the AI asked for, run only once approved
+fn tool_confirmation<'a>(
+    request: &ToolRequest,
+) -> Element<'a, MainMessage> {
+    let calls = request.calls.iter().fold(
+        Column::new().spacing(2),
+        |calls, call| {
+            calls.push(
+                text(format!(
+                    "- {}",
+                    tools::describe(call)
+                ))
+                .size(14),
+            )
+        },
+    );
+
+    Column::new()
+        .push(vertical_space().height(4))
+        .push(text(t!("tools-request")))
+        .push(Scrollable::new(calls).height(Length::Fill))
+        .push(
+            Row::new()
+                .push(action_button(
+                    t!("tools-allow"),
+                    MainMessage::ApproveToolCalls,
+                ))
+                .push(action_button(
+                    t!("tools-deny"),
+                    MainMessage::DenyToolCalls,
+                )),
+        )
+        .padding([0, PADDING_SIZE])
+        .into()
+}
+
+fn edit_prompt_bar<'a>() -> Element<'a, MainMessage> {
+    Row::new()
+        .push(
+            text(t!("editing-prompt"))
+                .size(14)
+                .width(Length::Fill),
+        )
+        .push(action_button(
+            t!("cancel"),
+            MainMessage::CancelEdit,
+        ))
+        .align_items(Alignment::Center)
+        .padding([0, 0, 4, 0])
+        .into()
+}
+
+/// Arrows to flip between the alternatives of a message,
+/// hidden when there is only one
+fn branch_switcher<'a>(
+    conversation: &Conversation,
+    id: usize,
+    label: &str,
+) -> Element<'a, MainMessage> {
+    let (position, count) =
+        conversation.branch_position(id);
+    if count < 2 {
+        return Row::new().into();
+    }
+
+    Row::new()
+        .push(icon_button(
+            text("<").size(14),
+            t!("branch-previous"),
+            MainMessage::PreviousBranch(id),
+        ))
+        .push(
+            text(format!("{label} {position}/{count}"))
+                .size(14),
+        )
+        .push(icon_button(
+            text(">").size(14),
+            t!("branch-next"),
+            MainMessage::NextBranch(id),
+        ))
+        .align_items(Alignment::Center)
+        .into()
+}
+
+fn action_button<'a>(
+    label: impl ToString,
+    on_press: MainMessage,
+) -> Element<'a, MainMessage> {
+    focusable(
+        button(text(label).size(14))
+            .on_press(on_press.clone())
+            .style(get_btn_transparent_style()),
+    )
+    .on_press(on_press)
+    .into()
+}
+
+/// Button without text, described in a tooltip
+fn icon_button<'a>(
+    icon: impl Into<Element<'a, MainMessage>>,
+    description: String,
+    on_press: MainMessage,
+) -> Element<'a, MainMessage> {
+    focusable(
+        tooltip(
+            button(icon)
+                .on_press(on_press.clone())
+                .style(get_btn_transparent_style()),
+            text(description).size(12),
+            tooltip::Position::Bottom,
+        )
+        .style(iced::theme::Container::Box),
+    )
+    .on_press(on_press)
+    .into()
+}
+
+pub fn conversations_sidebar<'a>(
+    store: &ConversationStore,
+    renaming: &Option<(u64, String)>,
+    notice: &Option<String>,
+    import_path: &str,
+) -> impl Into<Element<'a, MainMessage>> {
+    let selected = store.selected_id();
+
+    let list = store.sorted().into_iter().fold(
+        Column::new().spacing(2),
+        |list, saved| {
+            let is_selected = saved.id == selected;
+            list.push(conversation_entry(
+                saved,
+                is_selected,
+                renaming,
+            ))
+            .push_maybe(
+                is_selected
+                    .then(|| conversation_actions(saved)),
+            )
+        },
+    );
+
+    Column::new()
+        .push(action_button(
+            t!("conversation-new"),
+            MainMessage::NewConversation,
+        ))
+        .push(horizontal_rule(1))
+        .push(Scrollable::new(list).height(Length::Fill))
+        .push(
+            Row::new()
+                .push(
+                    text_input(
+                        &t!("import-placeholder"),
+                        import_path,
+                    )
+                    .size(14)
+                    .padding(4)
+                    .style(get_text_input_style())
+                    .on_input(MainMessage::UpdateImportPath)
+                    .on_submit(
+                        MainMessage::ImportConversation,
+                    ),
+                )
+                .push(action_button(
+                    t!("import"),
+                    MainMessage::ImportConversation,
+                ))
+                .align_items(Alignment::Center),
+        )
+        .push_maybe(
+            notice
+                .as_ref()
+                .map(|notice| text(notice).size(12)),
+        )
+        .width(Length::Fixed(220.))
+}
+
+/// Title of a conversation in the sidebar, or the field to
+/// rename it
+fn conversation_entry<'a>(
+    saved: &SavedConversation,
+    is_selected: bool,
+    renaming: &Option<(u64, String)>,
+) -> Element<'a, MainMessage> {
+    if let Some((_, title)) =
+        renaming.as_ref().filter(|(id, _)| *id == saved.id)
+    {
+        return text_input(
+            &t!("conversation-title"),
+            title,
+        )
+        .size(14)
+        .padding(4)
+        .style(get_text_input_style())
+        .on_input(MainMessage::UpdateConversationTitle)
+        .on_submit(MainMessage::ConfirmRenameConversation)
+        .into();
+    }
+
+    let title = saved.display_title();
+    let title = match (is_selected, saved.pinned) {
+        (true, true) => format!("> * {title}"),
+        (true, false) => format!("> {title}"),
+        (false, true) => format!("* {title}"),
+        (false, false) => title,
+    };
+    let select = MainMessage::SelectConversation(saved.id);
+
+    focusable(
+        button(text(title).size(14))
+            .on_press(select.clone())
+            .width(Length::Fill)
+            .style(get_btn_transparent_style()),
+    )
+    .on_press(select)
+    .into()
+}
+
+/// Buttons under the selected conversation
+fn conversation_actions<'a>(
+    saved: &SavedConversation,
+) -> Element<'a, MainMessage> {
+    let pin_label = if saved.pinned {
+        "conversation-unpin"
+    } else {
+        "conversation-pin"
+    };
+
+    let action_row = Row::new()
+        .push(action_button(
+            t!("conversation-rename"),
+            MainMessage::StartRenameConversation(saved.id),
+        ))
+        .push(action_button(
+            t!(pin_label),
+            MainMessage::TogglePinConversation(saved.id),
+        ))
+        .push(action_button(
+            t!("conversation-delete"),
+            MainMessage::DeleteConversation(saved.id),
+        ));
+
+    let export_row = ExportFormat::ALL.iter().fold(
+        Row::new()
+            .align_items(Alignment::Center)
+            .push(text(t!("conversation-export")).size(14)),
+        |row, format| {
+            row.push(action_button(
+                format.to_string(),
+                MainMessage::ExportConversation(
+                    saved.id, *format,
+                ),
+            ))
+        },
+    );
+
+    Column::new().push(action_row).push(export_row).into()
+}
+
+pub fn search_page_content<'a>(
+    query: &str,
+    results: &[SearchHit],
+) -> impl Into<Element<'a, MainMessage>> {
+    let highlight = current_theme().accent;
+    let no_match =
+        !query.trim().is_empty() && results.is_empty();
+
+    let results = results.iter().fold(
+        Column::new().spacing(4),
+        |list, hit| {
+            let details = match (&hit.role, &hit.model) {
+                (Role::Assistant, Some(model)) => {
+                    format!(
+                        "{} - {} - {model}",
+                        hit.title, hit.date
+                    )
+                }
+                _ => {
+                    format!("{} - {}", hit.title, hit.date)
+                }
+            };
+
+            let open = MainMessage::OpenSearchResult(
+                hit.conversation,
+                hit.message,
+            );
+            list.push(
+                focusable(
+                    button(
+                        Column::new()
+                            .push(text(details).size(12))
+                            .push(highlighted_text(
+                                &hit.snippet,
+                                &hit.matches,
+                                highlight,
+                            )),
+                    )
+                    .on_press(open.clone())
+                    .width(Length::Fill)
+                    .style(get_btn_transparent_style()),
+                )
+                .on_press(open),
+            )
+        },
+    );
+
+    Column::new()
+        .push(
+            text_input(&t!("search-placeholder"), query)
+                .padding(PADDING_SIZE)
+                .size(16)
+                .style(get_text_input_style())
+                .on_input(MainMessage::UpdateSearchQuery),
+        )
+        .push(vertical_space().height(4))
+        .push_maybe(
+            no_match.then(|| {
+                text(t!("search-no-match")).size(14)
+            }),
+        )
+        .push(Scrollable::new(results).height(Length::Fill))
+}
+
+pub fn compare_page_content<'a>(
+    installed_models: &[String],
+    comparison: &Comparison,
+) -> impl Into<Element<'a, MainMessage>> {
+    let models = installed_models.iter().fold(
+        Row::new().spacing(PADDING_SIZE),
+        |row, model| {
+            let name = model.clone();
+            let is_checked =
+                comparison.models.contains(model);
+            row.push(
+                focusable(
+                    checkbox(model.as_str(), is_checked)
+                        .on_toggle(move |on| {
+                            MainMessage::ToggleCompareModel(
+                                name.clone(),
+                                on,
+                            )
+                        })
+                        .size(14)
+                        .text_size(14),
+                )
+                .on_press(
+                    MainMessage::ToggleCompareModel(
+                        model.clone(),
+                        !is_checked,
+                    ),
+                ),
+            )
+        },
+    );
+
+    let answers =
+        comparison.answers.iter().enumerate().fold(
+            Row::new().spacing(PADDING_SIZE),
+            |row, (index, answer)| {
+                let (details, content, promote) =
+                    match &answer.result {
+                        None => (
+                            t!("compare-waiting"),
+                            String::new(),
+                            None,
+                        ),
+                        Some(Ok(result)) => (
+                            format!(
+                                "{:.1} s{}",
+                                result
+                                    .latency
+                                    .as_secs_f64(),
+                                result
+                                    .stats
+                                    .tokens_per_second()
+                                    .map(|rate| {
+                                        format!(
+                                            " - {}",
+                                            t!(
+                                                "stats-rate",
+                                                rate = format!(
+                                                    "{rate:.1}"
+                                                )
+                                            )
+                                        )
+                                    })
+                                    .unwrap_or_default()
+                            ),
+                            result.content.clone(),
+                            Some(
+                                MainMessage::PromoteAnswer(
+                                    index,
+                                ),
+                            ),
+                        ),
+                        Some(Err(err)) => (
+                            t!("compare-failed"),
+                            err.clone(),
+                            None,
+                        ),
+                    };
+
+                row.push(
+                    Column::new()
+                        .push(text(&answer.model).size(16))
+                        .push(text(details).size(12))
+                        .push(
+                            Scrollable::new(
+                                text(content).size(14),
+                            )
+                            .height(Length::Fill),
+                        )
+                        .push(
+                            focusable(
+                                button(
+                                    text(t!(
+                                        "compare-use-answer"
+                                    ))
+                                    .size(14),
+                                )
+                                .on_press_maybe(promote.clone())
+                                .style(
+                                    get_btn_transparent_style(),
+                                ),
+                            )
+                            .on_press_maybe(promote),
+                        )
+                        .width(Length::FillPortion(1)),
+                )
+            },
+        );
+
+    let run = (!comparison.is_running())
+        .then_some(MainMessage::RunComparison);
+
+    Column::new()
+        .push(
+            Row::new()
+                .push(
+                    text_input(
+                        &t!("compare-placeholder"),
+                        &comparison.prompt,
+                    )
+                    .padding(PADDING_SIZE)
+                    .size(16)
+                    .style(get_text_input_style())
+                    .on_input(
+                        MainMessage::UpdateComparePrompt,
+                    )
+                    .on_submit(MainMessage::RunComparison),
+                )
+                .push(
+                    focusable(
+                        button(
+                            text(t!("nav-compare"))
+                                .size(14),
+                        )
+                        .on_press_maybe(run.clone())
+                        .style(get_btn_transparent_style()),
+                    )
+                    .on_press_maybe(run),
+                )
+                .align_items(Alignment::Center),
+        )
+        .push(Scrollable::new(models).direction(
+            Direction::Horizontal(Properties::default()),
+        ))
+        .push(horizontal_rule(1))
+        .push(answers.height(Length::Fill))
+        .spacing(4)
+}
+
+/// Totals of every model that answered, one row each
+pub fn usage_page_content<'a>(
+    usage: &UsageStore,
+) -> impl Into<Element<'a, MainMessage>> {
+    let row = |cells: [String; 7]| {
+        cells.into_iter().enumerate().fold(
+            Row::new(),
+            |row, (i, cell)| {
+                row.push(text(cell).size(14).width(
+                    if i == 0 {
+                        Length::FillPortion(3)
+                    } else {
+                        Length::FillPortion(2)
+                    },
+                ))
+            },
+        )
+    };
+
+    let table = usage.models().fold(
+        Column::new().spacing(4),
+        |table, (model, usage)| {
+            table.push(row([
+                model.clone(),
+                usage.requests.to_string(),
+                usage.totals.prompt_eval_count.to_string(),
+                usage.totals.eval_count.to_string(),
+                usage
+                    .totals
+                    .tokens_per_second()
+                    .map(|rate| format!("{rate:.1}"))
+                    .unwrap_or("-".to_string()),
+                format!(
+                    "{:.2} s",
+                    usage.average_duration()
+                ),
+                format_date(usage.last_used),
+            ]))
+        },
+    );
+
+    Column::new()
+        .push(row([
+            t!("usage-model"),
+            t!("usage-replies"),
+            t!("usage-prompt-tokens"),
+            t!("usage-reply-tokens"),
+            t!("usage-tokens-per-second"),
+            t!("usage-average-time"),
+            t!("usage-last-used"),
+        ]))
+        .push(horizontal_rule(1))
+        .push_maybe(
+            usage
+                .is_empty()
+                .then(|| text(t!("usage-empty")).size(14)),
+        )
+        .push(Scrollable::new(table).height(Length::Fill))
+        .spacing(4)
+}
+
+/// Text with the given byte ranges drawn in another color
+fn highlighted_text<'a>(
+    content: &str,
+    matches: &[Range<usize>],
+    color: Color,
+) -> Element<'a, MainMessage> {
+    let mut row = Row::new();
+    let mut position = 0;
+
+    for range in matches {
+        if range.start > position {
+            row = row.push(
+                text(&content[position..range.start])
+                    .size(14),
+            );
+        }
+        row = row.push(
+            text(&content[range.clone()])
+                .size(14)
+                .style(color),
+        );
+        position = range.end;
+    }
+
+    row.push(text(&content[position..]).size(14)).into()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn settings_page_content<'a>(
-    models: &'a combo_box::State<String>,
-    current_model: Option<&String>,
+    installed_models: &[String],
+    settings: &ApplicationSettings,
+    context_length: u32,
+    context_length_input: &str,
+    is_indexing_documents: bool,
+    documents_status: &Option<DocumentsStatus>,
+    model_status: &Option<ModelStatus>,
+    themes: &[UserTheme],
 ) -> impl Into<Element<'a, MainMessage>> {
-    container(combo_box(
-        models,
-        "Select AI Model",
-        current_model,
-        MainMessage::UpdateConfigModel,
-    ))
+    let context_length_is_valid =
+        context_length_input.trim().is_empty()
+            || context_length_input
+                .trim()
+                .parse::<u32>()
+                .is_ok_and(|n| n >= MIN_NUM_CTX);
+    let system_language = t!("settings-language-system");
+    let index = (!is_indexing_documents
+        && settings.documents_dir.is_some())
+    .then_some(MainMessage::IndexDocuments);
+    let language_names =
+        std::iter::once(system_language.clone())
+            .chain(
+                Language::ALL
+                    .iter()
+                    .map(|language| language.to_string()),
+            )
+            .collect::<Vec<_>>();
+
+    Column::new()
+        .push(settings_row(
+            &t!("settings-language"),
+            keyboard_pick_list(
+                language_names,
+                settings
+                    .language
+                    .map(|language| language.to_string())
+                    .unwrap_or(system_language),
+                |name| {
+                    MainMessage::SelectLanguage(
+                        Language::ALL.into_iter().find(
+                            |language| {
+                                language.to_string() == name
+                            },
+                        ),
+                    )
+                },
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-reply-language"),
+            keyboard_checkbox(
+                t!("settings-reply-in-language"),
+                settings.reply_in_ui_language,
+                MainMessage::ToggleReplyInUiLanguage,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-model"),
+            keyboard_pick_list(
+                installed_models.to_vec(),
+                settings.ai_model.clone(),
+                MainMessage::UpdateConfigModel,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-keep-alive"),
+            Row::new()
+                .push(
+                    text_input(
+                        &t!("settings-keep-alive-placeholder"),
+                        &settings.keep_alive,
+                    )
+                    .on_input(
+                        MainMessage::UpdateConfigKeepAlive,
+                    )
+                    .style(get_text_input_style()),
+                )
+                .push(action_button(
+                    t!("settings-unload"),
+                    MainMessage::UnloadModel,
+                ))
+                .align_items(Alignment::Center),
+        ))
+        .push_maybe(
+            model_status
+                .as_ref()
+                .map(|status| text(status.to_string()).size(14)),
+        )
+        .push(settings_row(
+            &t!("settings-theme"),
+            Row::new()
+                .push(optional_pick_list(
+                    t!("settings-theme-follow-system"),
+                    themes.iter().map(|theme| {
+                        (theme.name.clone(), theme.display_name())
+                    }),
+                    settings.theme.clone(),
+                    MainMessage::SelectTheme,
+                ))
+                .push(action_button(
+                    t!("settings-theme-reload"),
+                    MainMessage::ReloadThemes,
+                ))
+                .align_items(Alignment::Center),
+        ))
+        .push(
+            text(t!(
+                "settings-themes-folder",
+                folder = THEMES_DIR
+            ))
+            .size(14),
+        )
+        .push(appearance_settings(settings))
+        .push(font_settings(settings))
+        .push(settings_row(
+            &t!("settings-context-window"),
+            text_input(
+                &t!(
+                    "settings-context-window-placeholder",
+                    tokens = context_length
+                ),
+                context_length_input,
+            )
+            .on_input(
+                MainMessage::UpdateConfigContextLength,
+            )
+            .style(get_text_input_style()),
+        ))
+        .push_maybe(
+            (!context_length_is_valid).then(|| {
+                text(t!(
+                    "settings-context-window-invalid",
+                    tokens = MIN_NUM_CTX
+                ))
+                .size(12)
+            }),
+        )
+        .push(settings_row(
+            &t!("settings-timeout"),
+            text_input(
+                &t!("settings-timeout-placeholder"),
+                &settings.request_timeout.to_string(),
+            )
+            .on_input(
+                MainMessage::UpdateConfigRequestTimeout,
+            )
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-retries"),
+            text_input(
+                &t!("settings-retries-placeholder"),
+                &settings.max_retries.to_string(),
+            )
+            .on_input(MainMessage::UpdateConfigMaxRetries)
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-documents"),
+            text_input(
+                &t!("settings-documents-placeholder"),
+                settings
+                    .documents_dir
+                    .as_deref()
+                    .unwrap_or(""),
+            )
+            .on_input(MainMessage::UpdateConfigDocumentsDir)
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-embedding-model"),
+            text_input(
+                &t!("settings-embedding-model"),
+                &settings.embedding_model,
+            )
+            .on_input(
+                MainMessage::UpdateConfigEmbeddingModel,
+            )
+            .style(get_text_input_style()),
+        ))
+        .push(
+            Row::new()
+                .push(
+                    text(
+                        documents_status
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_default(),
+                    )
+                    .size(14)
+                    .width(Length::Fill),
+                )
+                .push(
+                    focusable(
+                        button(
+                            text(t!(
+                                "settings-index-documents"
+                            ))
+                            .size(14),
+                        )
+                        .on_press_maybe(index.clone())
+                        .style(get_btn_transparent_style()),
+                    )
+                    .on_press_maybe(index),
+                )
+                .align_items(Alignment::Center),
+        )
+        .push(settings_row(
+            &t!("settings-tools"),
+            keyboard_checkbox(
+                t!("settings-tools-enabled"),
+                settings.tools_enabled,
+                MainMessage::ToggleTools,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-workspace"),
+            text_input(
+                &t!("settings-workspace-placeholder"),
+                settings
+                    .workspace_dir
+                    .as_deref()
+                    .unwrap_or(""),
+            )
+            .on_input(MainMessage::UpdateConfigWorkspaceDir)
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-allowed-commands"),
+            text_input(
+                "git status, git log",
+                &settings.allowed_commands.join(","),
+            )
+            .on_input(
+                MainMessage::UpdateConfigAllowedCommands,
+            )
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-json-output"),
+            keyboard_checkbox(
+                t!("settings-json-only"),
+                settings.json_mode,
+                MainMessage::ToggleJsonMode,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-json-schema"),
+            text_input(
+                &t!("settings-json-schema-placeholder"),
+                settings
+                    .json_schema
+                    .as_deref()
+                    .unwrap_or(""),
+            )
+            .on_input(MainMessage::UpdateConfigJsonSchema)
+            .style(get_text_input_style()),
+        ))
+        .push(settings_row(
+            &t!("settings-when-closed"),
+            keyboard_checkbox(
+                t!("settings-hide-on-close"),
+                settings.hide_on_close,
+                MainMessage::ToggleHideOnClose,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-window"),
+            keyboard_checkbox(
+                t!("settings-always-on-top"),
+                settings.always_on_top,
+                MainMessage::ToggleAlwaysOnTop,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-placement"),
+            keyboard_pick_list(
+                WindowPlacement::ALL.to_vec(),
+                settings.placement,
+                MainMessage::UpdateConfigPlacement,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-when-unfocused"),
+            keyboard_checkbox(
+                t!("settings-hide"),
+                settings.hide_on_focus_loss,
+                MainMessage::ToggleHideOnFocusLoss,
+            ),
+        ))
+        .push(
+            Row::new()
+                .push(action_button(
+                    t!("settings-usage"),
+                    MainMessage::ChangeView(
+                        RouterView::Usage,
+                    ),
+                ))
+                .push(action_button(
+                    t!("quit"),
+                    MainMessage::Exit,
+                )),
+        )
+        .spacing(PADDING_SIZE)
+}
+
+/// Opacity, border and corners of the overlay, the theme
+/// giving the ones not set
+fn appearance_settings<'a>(
+    settings: &ApplicationSettings,
+) -> Element<'a, MainMessage> {
+    let theme = current_theme();
+    let border_color =
+        settings.border_color.clone().unwrap_or_default();
+    let invalid_color = !border_color.is_empty()
+        && parse_hex_color(&border_color).is_none();
+
+    Column::new()
+        .push(settings_row(
+            &t!(
+                "settings-opacity",
+                percent =
+                    format!("{:.0}", theme.opacity * 100.0)
+            ),
+            keyboard_slider(
+                0.2..=1.0,
+                theme.opacity,
+                0.05,
+                MainMessage::UpdateConfigOpacity,
+            ),
+        ))
+        .push(settings_row(
+            &t!(
+                "settings-corner-radius",
+                pixels =
+                    format!("{:.0}", theme.border_radius)
+            ),
+            keyboard_slider(
+                0.0..=32.0,
+                theme.border_radius,
+                1.0,
+                MainMessage::UpdateConfigCornerRadius,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-border-color"),
+            text_input(
+                &t!("settings-border-color-placeholder"),
+                &border_color,
+            )
+            .on_input(MainMessage::UpdateConfigBorderColor)
+            .style(get_text_input_style()),
+        ))
+        .push_maybe(invalid_color.then(|| {
+            text(t!("settings-border-color-invalid"))
+                .size(14)
+        }))
+        .push(settings_row(
+            &t!("settings-accessibility"),
+            keyboard_checkbox(
+                t!("settings-high-contrast"),
+                settings.high_contrast,
+                MainMessage::ToggleHighContrast,
+            ),
+        ))
+        .push(action_button(
+            t!("settings-reset-appearance"),
+            MainMessage::ResetAppearance,
+        ))
+        .spacing(PADDING_SIZE)
+        .into()
+}
+
+/// Font families and zoom of the interface
+fn font_settings<'a>(
+    settings: &ApplicationSettings,
+) -> Element<'a, MainMessage> {
+    let zoom = fonts::clamp_zoom(settings.zoom);
+
+    Column::new()
+        .push(settings_row(
+            &t!("settings-ui-font"),
+            font_pick_list(
+                settings.ui_font.clone(),
+                MainMessage::UpdateConfigUiFont,
+            ),
+        ))
+        .push(text(t!("settings-ui-font-hint")).size(14))
+        .push(settings_row(
+            &t!("settings-reply-font"),
+            font_pick_list(
+                settings.reply_font.clone(),
+                MainMessage::UpdateConfigReplyFont,
+            ),
+        ))
+        .push(settings_row(
+            &t!("settings-code-font"),
+            font_pick_list(
+                settings.code_font.clone(),
+                MainMessage::UpdateConfigCodeFont,
+            ),
+        ))
+        .push(settings_row(
+            &t!(
+                "settings-zoom",
+                percent = format!("{:.0}", zoom * 100.0)
+            ),
+            keyboard_slider(
+                fonts::MIN_ZOOM as f32
+                    ..=fonts::MAX_ZOOM as f32,
+                zoom as f32,
+                fonts::ZOOM_STEP as f32,
+                |zoom| {
+                    MainMessage::UpdateConfigZoom(
+                        zoom.into(),
+                    )
+                },
+            ),
+        ))
+        .push(text(t!("settings-zoom-hint")).size(14))
+        .spacing(PADDING_SIZE)
+        .into()
+}
+
+/// Installed font families, after one standing for the
+/// default font
+fn font_pick_list<'a>(
+    selected: Option<String>,
+    on_select: fn(Option<String>) -> MainMessage,
+) -> Element<'a, MainMessage> {
+    optional_pick_list(
+        t!("settings-font-default"),
+        fonts::families()
+            .into_iter()
+            .map(|family| (family.clone(), family)),
+        selected,
+        on_select,
+    )
+}
+
+/// Option of a setting that may be left unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Choice {
+    /// Unset, with the label of what is used instead
+    Unset(String),
+    /// Value of the setting, with the label it is shown as
+    Value(String, String),
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Choice::Unset(label) => f.write_str(label),
+            Choice::Value(_, label) => f.write_str(label),
+        }
+    }
+}
+
+/// Pick list of values, given with their label, after an
+/// option leaving the setting unset, which no value can be
+/// mistaken for
+fn optional_pick_list<'a>(
+    unset_label: String,
+    values: impl Iterator<Item = (String, String)>,
+    selected: Option<String>,
+    on_select: impl Fn(Option<String>) -> MainMessage + 'a,
+) -> Element<'a, MainMessage> {
+    let unset = Choice::Unset(unset_label);
+    let options = std::iter::once(unset.clone())
+        .chain(values.map(|(value, label)| {
+            Choice::Value(value, label)
+        }))
+        .collect::<Vec<_>>();
+    let selected = selected.map_or(unset, |selected| {
+        options
+            .iter()
+            .find(|option| match option {
+                Choice::Value(value, _) => {
+                    *value == selected
+                }
+                Choice::Unset(_) => false,
+            })
+            .cloned()
+            .unwrap_or_else(|| {
+                Choice::Value(selected.clone(), selected)
+            })
+    });
+
+    keyboard_pick_list(options, selected, move |choice| {
+        on_select(match choice {
+            Choice::Unset(_) => None,
+            Choice::Value(value, _) => Some(value),
+        })
+    })
+}
+
+/// Pick list the arrow keys also step through, as its menu
+/// only opens with the mouse
+fn keyboard_pick_list<'a, T>(
+    options: Vec<T>,
+    selected: T,
+    on_select: impl Fn(T) -> MainMessage + 'a,
+) -> Element<'a, MainMessage>
+where
+    T: ToString + PartialEq + Clone + 'a,
+{
+    let count = options.len().max(1);
+    let position = options
+        .iter()
+        .position(|option| *option == selected)
+        .unwrap_or(0);
+    let step = |offset: usize| {
+        options
+            .get((position + offset) % count)
+            .cloned()
+            .map(&on_select)
+    };
+    let (previous, next) = (step(count - 1), step(1));
+
+    let pick_list =
+        pick_list(options, Some(selected), on_select)
+            .width(Length::Fill);
+    match (previous, next) {
+        (Some(previous), Some(next)) => {
+            focusable(pick_list)
+                .on_press(next.clone())
+                .on_step(previous, next)
+                .into()
+        }
+        _ => pick_list.into(),
+    }
+}
+
+/// Checkbox Enter and Space toggle once focused
+fn keyboard_checkbox<'a>(
+    label: String,
+    is_checked: bool,
+    on_toggle: fn(bool) -> MainMessage,
+) -> Element<'a, MainMessage> {
+    focusable(
+        checkbox(label, is_checked).on_toggle(on_toggle),
+    )
+    .on_press(on_toggle(!is_checked))
+    .into()
+}
+
+/// Slider the arrow keys move by one step once focused
+fn keyboard_slider<'a>(
+    range: RangeInclusive<f32>,
+    value: f32,
+    step: f32,
+    on_change: fn(f32) -> MainMessage,
+) -> Element<'a, MainMessage> {
+    let previous = (value - step).max(*range.start());
+    let next = (value + step).min(*range.end());
+
+    focusable(slider(range, value, on_change).step(step))
+        .on_step(on_change(previous), on_change(next))
+        .into()
+}
+
+fn settings_row<'a>(
+    label: &str,
+    input: impl Into<Element<'a, MainMessage>>,
+) -> Element<'a, MainMessage> {
+    Row::new()
+        .push(text(label).width(Length::Fill))
+        .push(container(input).width(Length::Fixed(300.)))
+        .align_items(Alignment::Center)
+        .into()
 }
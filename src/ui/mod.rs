@@ -1,7 +1,11 @@
+mod focus;
 pub mod gui;
 
 #[derive(Debug, Clone)]
 pub enum RouterView {
     Home,
     Settings,
+    Search,
+    Compare,
+    Usage,
 }
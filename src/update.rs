@@ -1,10 +1,47 @@
-use iced::{widget::combo_box, window, Command};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use iced::{widget, window, Command, Point, Size};
 
 use crate::{
-    ai::{ask_ai, check_ai_health},
-    config, App, AppState, MainMessage,
+    ai::{
+        ask_model, generate_title,
+        get_model_context_length, run_tools, send_request,
+        summarize_messages, unload_model, warm_up,
+        AiOutcome, AiReply, AiRequest, ModelStatus,
+        RetryState, MIN_NUM_CTX,
+    },
+    api::AiError,
+    chat::PendingReply,
+    config, conversations,
+    export::export_to_file,
+    fonts, geometry,
+    health::check_health,
+    i18n,
+    import::import_file,
+    pull::{PullEvent, PullState},
+    rag::{self, index_documents, DocumentsStatus},
+    search::SearchIndex,
+    styles::{system, theme},
+    t,
+    ui::RouterView,
+    usage, window_level, App, AppState, MainMessage,
 };
 
+/// Least time between two warm-ups triggered by showing the
+/// overlay
+const WARM_UP_INTERVAL: Duration = Duration::from_secs(60);
+/// Time the window has to stay still before its geometry
+/// is saved
+const GEOMETRY_SAVE_DELAY: Duration =
+    Duration::from_millis(500);
+/// Time without typing or dragging in the settings before
+/// they are saved
+const SETTINGS_SAVE_DELAY: Duration =
+    Duration::from_millis(500);
+
 pub fn handle_update(
     app: &mut App,
     message: MainMessage,
@@ -15,31 +52,151 @@ pub fn handle_update(
             Command::none()
         }
         MainMessage::SendToAI => {
-            let message_to_ai = app.text.clone();
-            app.loading = AppState::Loading;
-            Command::perform(
-                ask_ai(
-                    message_to_ai,
-                    app.config_settings.clone(),
-                ),
-                MainMessage::AIResponse,
-            )
+            let prompt = app.text.trim().to_string();
+            if prompt.is_empty() || is_replying(app) {
+                return Command::none();
+            }
+
+            let pending = if app.is_editing_prompt {
+                app.conversations
+                    .current()
+                    .edit_last_prompt(prompt)
+            } else {
+                Some(
+                    app.conversations
+                        .current()
+                        .follow_up(prompt),
+                )
+            };
+
+            match pending {
+                Some(pending) => send_to_ai(app, pending),
+                None => Command::none(),
+            }
+        }
+        MainMessage::RegenerateReply => {
+            if is_replying(app) {
+                return Command::none();
+            }
+            match app.conversations.current().regenerate() {
+                Some(pending) => send_to_ai(app, pending),
+                None => Command::none(),
+            }
+        }
+        MainMessage::EditLastPrompt => {
+            if let Some(prompt) =
+                app.conversations.current().last_prompt()
+            {
+                app.text = app
+                    .conversations
+                    .current()
+                    .message(prompt)
+                    .content
+                    .clone();
+                app.is_editing_prompt = true;
+            }
+            Command::none()
+        }
+        MainMessage::CancelEdit => {
+            app.text = "".to_string();
+            app.is_editing_prompt = false;
+            Command::none()
+        }
+        MainMessage::PreviousBranch(id) => {
+            app.conversations
+                .current_mut()
+                .select_sibling(id, false);
+            save_conversations(app);
+            Command::none()
+        }
+        MainMessage::NextBranch(id) => {
+            app.conversations
+                .current_mut()
+                .select_sibling(id, true);
+            save_conversations(app);
+            Command::none()
         }
         MainMessage::AIResponse(result) => {
+            let mut command = Command::none();
+
+            if let Err(err) = &result {
+                let attempt = app
+                    .retry
+                    .as_ref()
+                    .map_or(1, |retry| retry.attempt + 1);
+
+                if err.is_retryable()
+                    && attempt
+                        <= app.config_settings.max_retries
+                    && app.last_request.is_some()
+                {
+                    let retry = RetryState::new(
+                        attempt,
+                        app.config_settings.max_retries,
+                        err,
+                    );
+                    let delay = retry.delay;
+                    let revision = app.request_revision;
+                    app.retry = Some(retry);
+
+                    return Command::perform(
+                        tokio::time::sleep(delay),
+                        move |_| {
+                            MainMessage::RetryRequest(
+                                revision,
+                            )
+                        },
+                    );
+                }
+            }
+            app.retry = None;
+            app.last_request = None;
+
             match result {
-                Ok(response) => {
+                Ok(AiOutcome::ToolRequest(request)) => {
+                    app.error = None;
+                    app.tool_request = Some(request);
+                }
+                Ok(AiOutcome::Reply(response)) => {
                     app.error = None;
+                    if let Some(err) =
+                        &response.documents_error
+                    {
+                        app.documents_status = Some(
+                            DocumentsStatus::SearchFailed(
+                                err.clone(),
+                            ),
+                        );
+                    }
+                    app.usage.record(
+                        &response.model,
+                        &response.stats,
+                    );
+                    usage::save_usage(&app.usage);
 
-                    app.ai_response = response
-                        .replace('\t', " ")
-                        .replace("\n\n", "\n");
+                    if let Some((id, pending)) =
+                        app.pending_reply.take()
+                    {
+                        command = attach_reply(
+                            app, id, pending, response,
+                        );
+                    }
 
                     app.text = "".to_string();
-                    app.is_ai_api_live = true;
+                    app.is_editing_prompt = false;
                 }
                 Err(e) => {
-                    app.ai_response = "".to_string();
-                    app.error = Some(e);
+                    // The missing model prompt explains the
+                    // error already
+                    app.error = match e {
+                        AiError::ModelNotFound(model) => {
+                            app.missing_model = Some(model);
+                            None
+                        }
+                        e => Some(e.to_string()),
+                    };
+                    app.pending_reply = None;
+                    app.loading = AppState::Done;
 
                     return handle_update(
                         app,
@@ -49,36 +206,855 @@ pub fn handle_update(
             };
 
             app.loading = AppState::Done;
+            command
+        }
+        MainMessage::ApproveToolCalls
+        | MainMessage::DenyToolCalls => {
+            let Some(request) = app.tool_request.take()
+            else {
+                return Command::none();
+            };
+            let approved = matches!(
+                message,
+                MainMessage::ApproveToolCalls
+            );
+            app.loading = AppState::Loading;
+
+            Command::perform(
+                run_tools(
+                    request,
+                    approved,
+                    app.config_settings.clone(),
+                ),
+                MainMessage::ToolsRan,
+            )
+        }
+        MainMessage::ToolsRan(request) => {
+            send_request_to_ai(
+                app,
+                AiRequest::Continue(request),
+            )
+        }
+        MainMessage::RetryRequest(revision) => {
+            if revision != app.request_revision {
+                return Command::none();
+            }
+            match app.last_request.clone() {
+                Some(request) => {
+                    send_request_to_ai(app, request)
+                }
+                None => Command::none(),
+            }
+        }
+        MainMessage::ToggleJsonNode(pointer) => {
+            if !app.collapsed_json_nodes.remove(&pointer) {
+                app.collapsed_json_nodes.insert(pointer);
+            }
+            Command::none()
+        }
+        MainMessage::ToggleSidebar => {
+            app.show_sidebar = !app.show_sidebar;
+            app.view = RouterView::Home;
+            Command::none()
+        }
+        MainMessage::NewConversation => {
+            app.conversations.create();
+            reset_prompt(app);
+            save_conversations(app);
+            Command::none()
+        }
+        MainMessage::SelectConversation(id) => {
+            app.conversations.select(id);
+            reset_prompt(app);
+            save_conversations(app);
+            Command::none()
+        }
+        MainMessage::StartRenameConversation(id) => {
+            app.renaming_conversation = app
+                .conversations
+                .get(id)
+                .map(|saved| (id, saved.display_title()));
+            Command::none()
+        }
+        MainMessage::UpdateConversationTitle(title) => {
+            if let Some((_, current)) =
+                app.renaming_conversation.as_mut()
+            {
+                *current = title;
+            }
+            Command::none()
+        }
+        MainMessage::ConfirmRenameConversation => {
+            if let Some((id, title)) =
+                app.renaming_conversation.take()
+            {
+                app.conversations.rename(id, title);
+                save_conversations(app);
+            }
+            Command::none()
+        }
+        MainMessage::TogglePinConversation(id) => {
+            app.conversations.toggle_pin(id);
+            save_conversations(app);
+            Command::none()
+        }
+        MainMessage::DeleteConversation(id) => {
+            app.conversations.delete(id);
+            reset_prompt(app);
+            save_conversations(app);
+            Command::none()
+        }
+        MainMessage::ConversationTitleGenerated(
+            id,
+            title,
+        ) => {
+            if let Ok(title) = title {
+                if app
+                    .conversations
+                    .get(id)
+                    .is_some_and(|c| !c.titled)
+                {
+                    app.conversations.rename(id, title);
+                    save_conversations(app);
+                }
+            }
+            Command::none()
+        }
+        MainMessage::ExportConversation(id, format) => {
+            app.conversations_notice = app
+                .conversations
+                .get(id)
+                .map(|saved| {
+                    export_to_file(
+                        saved,
+                        &app.config_settings,
+                        format,
+                        None,
+                    )
+                })
+                .map(|result| match result {
+                    Ok(path) => t!(
+                        "conversation-exported",
+                        path = path.display().to_string()
+                    ),
+                    Err(err) => t!(
+                        "conversation-export-failed",
+                        error = err
+                    ),
+                });
+            Command::none()
+        }
+        MainMessage::UpdateImportPath(path) => {
+            app.import_path = path;
+            Command::none()
+        }
+        MainMessage::ImportConversation => {
+            let path =
+                PathBuf::from(app.import_path.trim());
+            let result =
+                import_file(&path, &mut app.conversations);
+
+            if result.is_ok() {
+                reset_prompt(app);
+                save_conversations(app);
+                app.import_path = "".to_string();
+            }
+
+            app.conversations_notice = Some(match result {
+                Ok(report) => report.to_string(),
+                Err(err) => {
+                    t!("import-failed", error = err)
+                }
+            });
+            Command::none()
+        }
+        MainMessage::UpdateConfigDocumentsDir(
+            directory,
+        ) => {
+            app.config_settings.documents_dir =
+                Some(directory)
+                    .filter(|d| !d.trim().is_empty());
+            rag::forget_index();
+            schedule_settings_save(app)
+        }
+        MainMessage::UpdateConfigEmbeddingModel(model) => {
+            app.config_settings.embedding_model = model;
+            schedule_settings_save(app)
+        }
+        MainMessage::ToggleTools(enabled) => {
+            app.config_settings.tools_enabled = enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigWorkspaceDir(
+            directory,
+        ) => {
+            app.config_settings.workspace_dir =
+                Some(directory)
+                    .filter(|d| !d.trim().is_empty());
+            schedule_settings_save(app)
+        }
+        MainMessage::UpdateConfigAllowedCommands(
+            commands,
+        ) => {
+            // Kept as typed so the field round-trips, blank
+            // entries never match a command
+            app.config_settings.allowed_commands = commands
+                .split(',')
+                .map(str::to_string)
+                .collect();
+            schedule_settings_save(app)
+        }
+        MainMessage::UpdateConfigRequestTimeout(value) => {
+            if let Ok(seconds) = value.trim().parse::<u64>()
+            {
+                app.config_settings.request_timeout =
+                    seconds.max(1);
+                return schedule_settings_save(app);
+            }
+            Command::none()
+        }
+        MainMessage::UpdateConfigMaxRetries(value) => {
+            if let Ok(retries) = value.trim().parse::<u32>()
+            {
+                app.config_settings.max_retries = retries;
+                return schedule_settings_save(app);
+            }
+            Command::none()
+        }
+        MainMessage::ToggleJsonMode(enabled) => {
+            app.config_settings.json_mode = enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigJsonSchema(schema) => {
+            app.config_settings.json_schema = Some(schema)
+                .filter(|s| !s.trim().is_empty());
+            schedule_settings_save(app)
+        }
+        MainMessage::IndexDocuments => {
+            app.is_indexing_documents = true;
+            app.documents_status =
+                Some(DocumentsStatus::Indexing);
+            Command::perform(
+                index_documents(
+                    app.config_settings.clone(),
+                ),
+                MainMessage::DocumentsIndexed,
+            )
+        }
+        MainMessage::DocumentsIndexed(result) => {
+            app.is_indexing_documents = false;
+            app.documents_status = Some(match result {
+                Ok(summary) => {
+                    DocumentsStatus::Indexed(summary)
+                }
+                Err(err) => {
+                    DocumentsStatus::IndexingFailed(err)
+                }
+            });
             Command::none()
         }
         MainMessage::ChangeView(view) => {
+            if let RouterView::Search = view {
+                app.search_index =
+                    SearchIndex::build(&app.conversations);
+                app.search_results =
+                    app.search_index.search(
+                        &app.conversations,
+                        &app.search_query,
+                    );
+            }
             app.view = view;
             Command::none()
         }
-        MainMessage::AiHealthCheck(is_live) => {
-            app.is_ai_api_live = is_live;
-            app.loading = AppState::Done;
+        MainMessage::UpdateSearchQuery(query) => {
+            app.search_results = app
+                .search_index
+                .search(&app.conversations, &query);
+            app.search_query = query;
             Command::none()
         }
-        MainMessage::RunAiHealthCheck => {
-            app.loading = AppState::Loading;
-            Command::perform(
-                check_ai_health(),
-                MainMessage::AiHealthCheck,
+        MainMessage::OpenSearchResult(id, message) => {
+            app.conversations.select(id);
+            app.conversations.current_mut().reveal(message);
+            reset_prompt(app);
+            save_conversations(app);
+            app.view = RouterView::Home;
+            Command::none()
+        }
+        MainMessage::UpdateComparePrompt(prompt) => {
+            app.comparison.prompt = prompt;
+            Command::none()
+        }
+        MainMessage::ToggleCompareModel(model, on) => {
+            app.comparison.toggle_model(model, on);
+            Command::none()
+        }
+        MainMessage::RunComparison => {
+            let prompt =
+                app.comparison.prompt.trim().to_string();
+            if prompt.is_empty()
+                || app.comparison.models.is_empty()
+                || app.comparison.is_running()
+            {
+                return Command::none();
+            }
+
+            let pending = app
+                .conversations
+                .current()
+                .follow_up(prompt);
+            let messages = app
+                .conversations
+                .current()
+                .request_history(&pending);
+            let run = app.comparison.start(
+                app.conversations.selected_id(),
+                pending,
+            );
+
+            Command::batch(
+                app.comparison.models.iter().enumerate().map(
+                    |(index, model)| {
+                        Command::perform(
+                            ask_model(
+                                messages.clone(),
+                                model.clone(),
+                                app.config_settings.clone(),
+                            ),
+                            move |result| {
+                                MainMessage::ComparisonAnswer(
+                                    run, index, result,
+                                )
+                            },
+                        )
+                    },
+                ),
+            )
+        }
+        MainMessage::ComparisonAnswer(
+            run,
+            index,
+            result,
+        ) => {
+            if let (Ok(answer), Some(compared)) =
+                (&result, app.comparison.answers.get(index))
+            {
+                if run == app.comparison.run {
+                    app.usage.record(
+                        &compared.model,
+                        &answer.stats,
+                    );
+                    usage::save_usage(&app.usage);
+                }
+            }
+            app.comparison.set_answer(run, index, result);
+            Command::none()
+        }
+        MainMessage::PromoteAnswer(index) => {
+            let Some((id, pending, model, answer)) =
+                app.comparison.take_answer(index)
+            else {
+                return Command::none();
+            };
+
+            app.conversations.select(id);
+            reset_prompt(app);
+            app.comparison.prompt = "".to_string();
+            app.view = RouterView::Home;
+            attach_reply(
+                app,
+                id,
+                pending,
+                AiReply {
+                    content: answer.content,
+                    model,
+                    citations: vec![],
+                    tools_used: vec![],
+                    structured: false,
+                    stats: answer.stats,
+                    documents_error: None,
+                },
             )
         }
+        MainMessage::AiHealthCheck(report) => {
+            app.health_failures = if report.is_live() {
+                0
+            } else {
+                app.health_failures.saturating_add(1)
+            };
+            app.health = Some(report);
+            Command::none()
+        }
+        MainMessage::RunAiHealthCheck => Command::perform(
+            check_health(),
+            MainMessage::AiHealthCheck,
+        ),
         MainMessage::UpdateConfigModel(new_model) => {
-            app.config_settings.ai_model = new_model;
+            app.config_settings.ai_model =
+                new_model.clone();
             config::save_settings(
                 app.config_settings.clone(),
             );
+            app.model_context_length = None;
+            if app.missing_model.take().is_some() {
+                app.error = None;
+            }
+            app.last_warm_up = None;
+            Command::batch([
+                Command::perform(
+                    get_model_context_length(new_model),
+                    MainMessage::ModelContextLength,
+                ),
+                handle_update(
+                    app,
+                    MainMessage::WarmUpModel,
+                ),
+            ])
+        }
+        MainMessage::UpdateConfigKeepAlive(keep_alive) => {
+            app.config_settings.keep_alive = keep_alive;
+            schedule_settings_save(app)
+        }
+        MainMessage::WarmUpModel => {
+            if app.last_warm_up.is_some_and(|time| {
+                time.elapsed() < WARM_UP_INTERVAL
+            }) {
+                return Command::none();
+            }
+            app.last_warm_up = Some(Instant::now());
+            app.model_status = Some(ModelStatus::Loading);
+            Command::perform(
+                warm_up(app.config_settings.clone()),
+                MainMessage::ModelWarmedUp,
+            )
+        }
+        MainMessage::ModelWarmedUp(result) => {
+            app.model_status = Some(match result {
+                Ok(()) => ModelStatus::Loaded(
+                    app.config_settings.ai_model.clone(),
+                ),
+                Err(err) => {
+                    app.last_warm_up = None;
+                    ModelStatus::LoadingFailed(err)
+                }
+            });
+            handle_update(
+                app,
+                MainMessage::RunAiHealthCheck,
+            )
+        }
+        MainMessage::UnloadModel => Command::perform(
+            unload_model(app.config_settings.clone()),
+            MainMessage::ModelUnloaded,
+        ),
+        MainMessage::ModelUnloaded(result) => {
+            app.last_warm_up = None;
+            app.model_status = Some(match result {
+                Ok(()) => ModelStatus::Unloaded(
+                    app.config_settings.ai_model.clone(),
+                ),
+                Err(err) => {
+                    ModelStatus::UnloadingFailed(err)
+                }
+            });
+            handle_update(
+                app,
+                MainMessage::RunAiHealthCheck,
+            )
+        }
+        MainMessage::UpdateConfigContextLength(value) => {
+            let num_ctx = value.trim().parse::<u32>();
+            app.context_length_input = value;
+
+            if app.context_length_input.trim().is_empty() {
+                app.config_settings.num_ctx = None;
+            } else if let Some(num_ctx) =
+                num_ctx.ok().filter(|&n| n >= MIN_NUM_CTX)
+            {
+                app.config_settings.num_ctx = Some(num_ctx);
+            } else {
+                return Command::none();
+            }
+            schedule_settings_save(app)
+        }
+        MainMessage::ModelContextLength(length) => {
+            app.model_context_length = length;
+            Command::none()
+        }
+        MainMessage::ConversationSummarized(
+            id,
+            node,
+            summary,
+        ) => {
+            app.summarizing = false;
+            if let (Ok(summary), Some(saved)) =
+                (summary, app.conversations.get_mut(id))
+            {
+                saved
+                    .conversation
+                    .set_summary(node, summary);
+                save_conversations(app);
+            }
             Command::none()
         }
         MainMessage::UpdateAvailableModels(models) => {
-            app.available_models =
-                combo_box::State::new(models);
+            app.installed_models = models;
+            Command::none()
+        }
+        MainMessage::ModelsChecked(Ok(models)) => {
+            let model = &app.config_settings.ai_model;
+            if !is_installed(&models, model) {
+                app.missing_model = Some(model.clone());
+            }
+            handle_update(
+                app,
+                MainMessage::UpdateAvailableModels(models),
+            )
+        }
+        MainMessage::ModelsChecked(Err(_)) => {
+            Command::none()
+        }
+        MainMessage::PullModel(model) => {
+            if app.pull.is_none() {
+                app.pull = Some(PullState::new(model));
+                app.error = None;
+            }
+            Command::none()
+        }
+        MainMessage::PullProgress(PullEvent::Progress(
+            status,
+        )) => {
+            if let Some(pull) = app.pull.as_mut() {
+                pull.update(status);
+            }
             Command::none()
         }
+        MainMessage::ColorSchemeChanged(scheme) => {
+            // The window is themed again when redrawn
+            system::set_color_scheme(scheme);
+            Command::none()
+        }
+        MainMessage::SelectTheme(name) => {
+            theme::select_theme(
+                &app.themes,
+                name.as_deref(),
+            );
+            app.config_settings.theme = name;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigOpacity(opacity) => {
+            app.config_settings.background_opacity =
+                Some(opacity);
+            save_appearance(app)
+        }
+        MainMessage::UpdateConfigBorderColor(color) => {
+            app.config_settings.border_color = Some(color)
+                .filter(|c| !c.trim().is_empty());
+            save_appearance(app)
+        }
+        MainMessage::UpdateConfigCornerRadius(radius) => {
+            app.config_settings.corner_radius =
+                Some(radius);
+            save_appearance(app)
+        }
+        MainMessage::ToggleHighContrast(enabled) => {
+            app.config_settings.high_contrast = enabled;
+            save_appearance(app)
+        }
+        MainMessage::ResetAppearance => {
+            app.config_settings.background_opacity = None;
+            app.config_settings.border_color = None;
+            app.config_settings.corner_radius = None;
+            save_appearance(app)
+        }
+        MainMessage::DragWindow => {
+            window::drag(window::Id::MAIN)
+        }
+        // Window events are scaled down by the zoom, unlike
+        // the commands and settings the geometry is given to
+        MainMessage::WindowResized(size) => {
+            let zoom = app_zoom(app) as f32;
+            app.window_geometry.width = size.width * zoom;
+            app.window_geometry.height = size.height * zoom;
+            schedule_geometry_save(app)
+        }
+        MainMessage::WindowMoved(x, y) => {
+            let zoom = app_zoom(app);
+            app.window_geometry.position = Some((
+                (x as f64 * zoom).round() as i32,
+                (y as f64 * zoom).round() as i32,
+            ));
+            schedule_geometry_save(app)
+        }
+        MainMessage::SaveWindowGeometry(revision) => {
+            if revision != app.geometry_revision {
+                return Command::none();
+            }
+            // The expanded mode is only for the answer at
+            // hand
+            let mut geometry = app.window_geometry;
+            if let Some(size) = app.size_before_expanding {
+                geometry.width = size.width;
+                geometry.height = size.height;
+            }
+            Command::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        geometry::current_monitor(&geometry)
+                    })
+                    .await
+                    .ok()
+                },
+                move |monitor| {
+                    MainMessage::WindowMonitorFound(
+                        revision, geometry, monitor,
+                    )
+                },
+            )
+        }
+        MainMessage::WindowMonitorFound(
+            revision,
+            geometry,
+            Some(monitor),
+        ) if revision == app.geometry_revision => {
+            geometry::save_geometry(
+                &mut app.config_settings,
+                monitor,
+                geometry,
+            );
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::WindowMonitorFound(..) => {
+            Command::none()
+        }
+        MainMessage::SaveSettings(revision) => {
+            if revision == app.settings_revision {
+                config::save_settings(
+                    app.config_settings.clone(),
+                );
+            }
+            Command::none()
+        }
+        MainMessage::ToggleExpanded => {
+            let size = match app
+                .size_before_expanding
+                .take()
+            {
+                Some(size) => size,
+                None => {
+                    let size = app.window_geometry.size();
+                    app.size_before_expanding = Some(size);
+                    Size::new(
+                        size.width,
+                        size.height
+                            .max(geometry::EXPANDED_HEIGHT),
+                    )
+                }
+            };
+            window::resize(window::Id::MAIN, size)
+        }
+        MainMessage::CloseWindow => {
+            if app.config_settings.hide_on_close {
+                hide_window(app)
+            } else {
+                handle_update(app, MainMessage::Exit)
+            }
+        }
+        MainMessage::WindowUnfocused => {
+            if app.config_settings.hide_on_focus_loss {
+                hide_window(app)
+            } else {
+                Command::none()
+            }
+        }
+        MainMessage::ShowWindow => {
+            app.is_hidden = false;
+            find_placement(app, MainMessage::ShowWindowAt)
+        }
+        MainMessage::ShowWindowAt(position) => {
+            Command::batch(
+                position
+                    .map(|position| {
+                        window::move_to(
+                            window::Id::MAIN,
+                            position,
+                        )
+                    })
+                    .into_iter()
+                    .chain([
+                        window::change_mode(
+                            window::Id::MAIN,
+                            window::Mode::Windowed,
+                        ),
+                        window::change_level(
+                            window::Id::MAIN,
+                            window_level(
+                                app.config_settings
+                                    .always_on_top,
+                            ),
+                        ),
+                        window::gain_focus(
+                            window::Id::MAIN,
+                        ),
+                    ]),
+            )
+        }
+        MainMessage::ToggleAlwaysOnTop(enabled) => {
+            app.config_settings.always_on_top = enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            window::change_level(
+                window::Id::MAIN,
+                window_level(enabled),
+            )
+        }
+        MainMessage::UpdateConfigPlacement(placement) => {
+            app.config_settings.placement = placement;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            find_placement(app, MainMessage::MoveWindowTo)
+        }
+        MainMessage::MoveWindowTo(position) => {
+            match position {
+                Some(position) => window::move_to(
+                    window::Id::MAIN,
+                    position,
+                ),
+                None => Command::none(),
+            }
+        }
+        MainMessage::ToggleHideOnClose(enabled) => {
+            app.config_settings.hide_on_close = enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::ToggleHideOnFocusLoss(enabled) => {
+            app.config_settings.hide_on_focus_loss =
+                enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::SelectLanguage(language) => {
+            app.config_settings.language = language;
+            i18n::set_language(language);
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::ToggleReplyInUiLanguage(enabled) => {
+            app.config_settings.reply_in_ui_language =
+                enabled;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigUiFont(family) => {
+            app.config_settings.ui_font = family;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigReplyFont(family) => {
+            app.config_settings.reply_font = family;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigCodeFont(family) => {
+            app.config_settings.code_font = family;
+            config::save_settings(
+                app.config_settings.clone(),
+            );
+            Command::none()
+        }
+        MainMessage::UpdateConfigZoom(zoom) => {
+            set_zoom(app, zoom)
+        }
+        MainMessage::ZoomIn => {
+            let zoom = app.config_settings.zoom;
+            set_zoom(app, zoom + fonts::ZOOM_STEP)
+        }
+        MainMessage::ZoomOut => {
+            let zoom = app.config_settings.zoom;
+            set_zoom(app, zoom - fonts::ZOOM_STEP)
+        }
+        MainMessage::ResetZoom => {
+            set_zoom(app, fonts::DEFAULT_ZOOM)
+        }
+        MainMessage::FocusNext => widget::focus_next(),
+        MainMessage::FocusPrevious => {
+            widget::focus_previous()
+        }
+        MainMessage::ReloadThemes => {
+            app.themes = theme::load_themes();
+            theme::select_theme(
+                &app.themes,
+                app.config_settings.theme.as_deref(),
+            );
+            Command::none()
+        }
+        MainMessage::PullProgress(PullEvent::Finished(
+            result,
+        )) => {
+            let Some(pull) = app.pull.take() else {
+                return Command::none();
+            };
+
+            match result {
+                Ok(()) => {
+                    if app.missing_model.as_ref()
+                        == Some(&pull.model)
+                    {
+                        app.missing_model = None;
+                        app.error = None;
+                    }
+                    Command::batch([
+                        handle_update(
+                            app,
+                            MainMessage::GetAvailableModels,
+                        ),
+                        Command::perform(
+                            get_model_context_length(
+                                app.config_settings
+                                    .ai_model
+                                    .clone(),
+                            ),
+                            MainMessage::ModelContextLength,
+                        ),
+                    ])
+                }
+                Err(err) => {
+                    app.error = Some(t!(
+                        "model-pull-failed",
+                        model = pull.model,
+                        error = err
+                    ));
+                    Command::none()
+                }
+            }
+        }
         MainMessage::GetAvailableModels => {
             Command::perform(
                 crate::ai::get_ai_models_installed(),
@@ -97,7 +1073,243 @@ pub fn handle_update(
             )
         }
         MainMessage::Exit => {
+            // Changes waiting to be saved would be lost
+            config::save_settings(
+                app.config_settings.clone(),
+            );
             window::close(window::Id::MAIN)
         }
     }
 }
+
+fn send_to_ai(
+    app: &mut App,
+    pending: PendingReply,
+) -> Command<MainMessage> {
+    let messages = app
+        .conversations
+        .current()
+        .request_history(&pending);
+    app.pending_reply =
+        Some((app.conversations.selected_id(), pending));
+    app.tool_request = None;
+    app.retry = None;
+
+    send_request_to_ai(app, AiRequest::Ask(messages))
+}
+
+/// Save the conversations, telling the user when it fails
+fn save_conversations(app: &mut App) {
+    if let Err(err) = conversations::save_conversations(
+        &app.conversations,
+    ) {
+        app.error = Some(t!(
+            "conversations-save-failed",
+            error = err
+        ));
+    }
+}
+
+/// A reply is on its way or waiting to be asked again, so
+/// another prompt would take its place
+fn is_replying(app: &App) -> bool {
+    matches!(app.loading, AppState::Loading)
+        || app.retry.is_some()
+}
+
+/// Send a request, keeping it to resend it if it fails
+fn send_request_to_ai(
+    app: &mut App,
+    request: AiRequest,
+) -> Command<MainMessage> {
+    app.last_request = Some(request.clone());
+    app.request_revision += 1;
+    app.loading = AppState::Loading;
+
+    Command::perform(
+        send_request(request, app.config_settings.clone()),
+        MainMessage::AIResponse,
+    )
+}
+
+/// Store a reply in the conversation it was asked from, and
+/// name the conversation after its first exchange
+fn attach_reply(
+    app: &mut App,
+    id: u64,
+    pending: PendingReply,
+    reply: AiReply,
+) -> Command<MainMessage> {
+    let context_length = app.context_length();
+    let Some(saved) = app.conversations.get_mut(id) else {
+        return Command::none();
+    };
+
+    saved.conversation.attach_reply(pending, reply);
+    app.collapsed_json_nodes.clear();
+
+    let first_prompt = (!saved.titled)
+        .then(|| saved.conversation.last_prompt())
+        .flatten()
+        .filter(|_| {
+            saved.conversation.active_path().len() == 2
+        })
+        .map(|prompt| {
+            saved
+                .conversation
+                .message(prompt)
+                .content
+                .clone()
+        });
+
+    let summary = (!app.summarizing
+        && saved.conversation.needs_summary(
+            context_length,
+            rag::excerpts_tokens(&app.config_settings),
+        ))
+    .then(|| {
+        saved
+            .conversation
+            .summary_candidates(context_length)
+    })
+    .flatten();
+
+    app.conversations.touch(id);
+    save_conversations(app);
+
+    let mut commands = vec![];
+    if let Some(prompt) = first_prompt {
+        commands.push(Command::perform(
+            generate_title(
+                prompt,
+                app.config_settings.clone(),
+            ),
+            move |title| {
+                MainMessage::ConversationTitleGenerated(
+                    id, title,
+                )
+            },
+        ));
+    }
+    if let Some((node, messages)) = summary {
+        app.summarizing = true;
+        commands.push(Command::perform(
+            summarize_messages(
+                messages,
+                app.config_settings.clone(),
+            ),
+            move |summary| {
+                MainMessage::ConversationSummarized(
+                    id, node, summary,
+                )
+            },
+        ));
+    }
+
+    Command::batch(commands)
+}
+
+/// Whether a model is in the list, `name` standing for
+/// `name:latest`
+fn is_installed(models: &[String], model: &str) -> bool {
+    models.iter().any(|installed| {
+        installed == model
+            || *installed == format!("{model}:latest")
+    })
+}
+
+fn hide_window(app: &mut App) -> Command<MainMessage> {
+    if app.is_hidden {
+        return Command::none();
+    }
+    app.is_hidden = true;
+
+    window::change_mode(
+        window::Id::MAIN,
+        window::Mode::Hidden,
+    )
+}
+
+/// Save the geometry once the window has not moved nor been
+/// resized for a moment
+fn schedule_geometry_save(
+    app: &mut App,
+) -> Command<MainMessage> {
+    app.geometry_revision += 1;
+    let revision = app.geometry_revision;
+
+    Command::perform(
+        tokio::time::sleep(GEOMETRY_SAVE_DELAY),
+        move |_| MainMessage::SaveWindowGeometry(revision),
+    )
+}
+
+/// Find where the placement puts the window off the UI
+/// thread, as it runs `xrandr` and `xdotool`
+fn find_placement(
+    app: &App,
+    on_found: fn(Option<Point>) -> MainMessage,
+) -> Command<MainMessage> {
+    let placement = app.config_settings.placement;
+    let geometry = app.window_geometry;
+
+    Command::perform(
+        async move {
+            tokio::task::spawn_blocking(move || {
+                geometry::placement_position(
+                    placement, &geometry,
+                )
+            })
+            .await
+            .ok()
+            .flatten()
+        },
+        on_found,
+    )
+}
+
+/// Save the settings once they have not changed for a
+/// moment, e.g. at the end of a slider drag
+fn schedule_settings_save(
+    app: &mut App,
+) -> Command<MainMessage> {
+    app.settings_revision += 1;
+    let revision = app.settings_revision;
+
+    Command::perform(
+        tokio::time::sleep(SETTINGS_SAVE_DELAY),
+        move |_| MainMessage::SaveSettings(revision),
+    )
+}
+
+/// Redraw with the appearance settings right away, saving
+/// them once they settle
+fn save_appearance(app: &mut App) -> Command<MainMessage> {
+    theme::apply_appearance(&app.config_settings);
+    schedule_settings_save(app)
+}
+
+fn app_zoom(app: &App) -> f64 {
+    fonts::clamp_zoom(app.config_settings.zoom)
+}
+
+fn set_zoom(
+    app: &mut App,
+    zoom: f64,
+) -> Command<MainMessage> {
+    app.config_settings.zoom = fonts::round_zoom(zoom);
+    schedule_settings_save(app)
+}
+
+fn reset_prompt(app: &mut App) {
+    // Leaving the conversation drops the reply waiting for
+    // tool calls to be approved
+    if app.tool_request.take().is_some() {
+        app.pending_reply = None;
+    }
+    app.text = "".to_string();
+    app.is_editing_prompt = false;
+    app.renaming_conversation = None;
+    app.conversations_notice = None;
+    app.error = None;
+}
@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{debug, t};
+
+const USAGE_FILE_NAME: &str = "usage.json";
+
+/// Counters Ollama reports once an answer is complete,
+/// durations being in nanoseconds
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+)]
+#[serde(default)]
+pub struct GenerationStats {
+    pub prompt_eval_count: u64,
+    pub eval_count: u64,
+    pub eval_duration: u64,
+    pub load_duration: u64,
+    pub total_duration: u64,
+}
+
+impl GenerationStats {
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        (self.eval_duration > 0).then(|| {
+            self.eval_count as f64
+                / Duration::from_nanos(self.eval_duration)
+                    .as_secs_f64()
+        })
+    }
+
+    /// Sum of the requests an answer took, e.g. tool rounds
+    pub fn add(&mut self, other: &Self) {
+        self.prompt_eval_count += other.prompt_eval_count;
+        self.eval_count += other.eval_count;
+        self.eval_duration += other.eval_duration;
+        self.load_duration += other.load_duration;
+        self.total_duration += other.total_duration;
+    }
+}
+
+impl std::fmt::Display for GenerationStats {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.write_str(&t!(
+            "stats-tokens",
+            prompt = self.prompt_eval_count,
+            reply = self.eval_count
+        ))?;
+        if let Some(rate) = self.tokens_per_second() {
+            let rate = format!("{rate:.1}");
+            write!(
+                f,
+                " - {}",
+                t!("stats-rate", rate = rate)
+            )?;
+        }
+        write!(
+            f,
+            " - {}",
+            t!(
+                "stats-durations",
+                load = format!(
+                    "{:.2}",
+                    seconds(self.load_duration)
+                ),
+                total = format!(
+                    "{:.2}",
+                    seconds(self.total_duration)
+                )
+            )
+        )
+    }
+}
+
+/// Totals of a model over every answer it gave
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Default,
+)]
+#[serde(default)]
+pub struct ModelUsage {
+    pub requests: u64,
+    pub totals: GenerationStats,
+    /// Unix timestamp of the last answer
+    pub last_used: u64,
+}
+
+impl ModelUsage {
+    pub fn average_duration(&self) -> f64 {
+        if self.requests == 0 {
+            0.
+        } else {
+            seconds(self.totals.total_duration)
+                / self.requests as f64
+        }
+    }
+}
+
+#[derive(
+    serde::Deserialize,
+    serde::Serialize,
+    Debug,
+    Clone,
+    Default,
+)]
+pub struct UsageStore {
+    models: BTreeMap<String, ModelUsage>,
+}
+
+impl UsageStore {
+    pub fn record(
+        &mut self,
+        model: &str,
+        stats: &GenerationStats,
+    ) {
+        let usage = self
+            .models
+            .entry(model.to_string())
+            .or_default();
+        usage.requests += 1;
+        usage.totals.add(stats);
+        usage.last_used = crate::conversations::now();
+    }
+
+    pub fn models(
+        &self,
+    ) -> impl Iterator<Item = (&String, &ModelUsage)> {
+        self.models.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+}
+
+pub fn seconds(nanoseconds: u64) -> f64 {
+    Duration::from_nanos(nanoseconds).as_secs_f64()
+}
+
+pub fn load_usage() -> UsageStore {
+    let usage = std::fs::read_to_string(USAGE_FILE_NAME)
+        .ok()
+        .and_then(|contents| {
+            serde_json::from_str::<UsageStore>(&contents)
+                .ok()
+        })
+        .unwrap_or_default();
+
+    debug!(usage.models.len());
+
+    usage
+}
+
+pub fn save_usage(usage: &UsageStore) {
+    let result = serde_json::to_string(usage)
+        .map_err(|err| err.to_string())
+        .and_then(|usage| {
+            std::fs::write(USAGE_FILE_NAME, usage)
+                .map_err(|err| err.to_string())
+        });
+
+    if let Err(err) = result {
+        debug!(err);
+    }
+}